@@ -0,0 +1,298 @@
+use std::fs::File;
+use std::io::{BufRead, BufReader, Write};
+use std::path::Path;
+
+use crate::{Classifier, Prediction};
+
+/// A line of an unlabeled file together with its verdict.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ScoredText {
+    pub text: String,
+    pub prediction: Prediction,
+}
+
+impl Classifier {
+    /// Lazily classifies every non-empty line of an unlabeled file, holding only one
+    /// line in memory at a time.
+    pub fn predict_file_stream<P: AsRef<Path>>(
+        &self,
+        path: P,
+    ) -> Result<impl Iterator<Item = Result<ScoredText, std::io::Error>> + '_, std::io::Error> {
+        Ok(self.predict_stream(BufReader::new(File::open(path)?)))
+    }
+
+    /// Like [`Classifier::predict_file_stream`] for any buffered reader.
+    pub fn predict_stream<'a, R: BufRead + 'a>(
+        &'a self,
+        input: R,
+    ) -> impl Iterator<Item = Result<ScoredText, std::io::Error>> + 'a {
+        input
+            .lines()
+            .filter(|line| !matches!(line, Ok(line) if line.trim().is_empty()))
+            .map(move |line| {
+                let text = line?;
+                let prediction = self.predict(&text).map_err(|err| {
+                    std::io::Error::new(std::io::ErrorKind::Other, err.to_string())
+                })?;
+                Ok(ScoredText { text, prediction })
+            })
+    }
+}
+
+/// Writes CSV one record at a time, quoting fields that contain a comma, a double
+/// quote or a line break as in RFC 4180, with embedded quotes doubled. Records end
+/// with `\n`.
+#[derive(Debug)]
+pub struct CsvWriter<W: Write> {
+    output: W,
+}
+
+/// Columns of the rows written by [`CsvWriter::write_scored`].
+pub const SCORED_CSV_HEADER: [&str; 3] = ["sms", "spam", "spam_probability"];
+
+impl<W: Write> CsvWriter<W> {
+    pub fn new(output: W) -> Self {
+        Self { output }
+    }
+
+    pub fn write_record<I>(&mut self, fields: I) -> Result<(), std::io::Error>
+    where
+        I: IntoIterator,
+        I::Item: AsRef<str>,
+    {
+        for (index, field) in fields.into_iter().enumerate() {
+            if index > 0 {
+                self.output.write_all(b",")?;
+            }
+            let field = field.as_ref();
+            if field.contains([',', '"', '\n', '\r']) {
+                write!(self.output, "\"{}\"", field.replace('"', "\"\""))?;
+            } else {
+                self.output.write_all(field.as_bytes())?;
+            }
+        }
+        self.output.write_all(b"\n")
+    }
+
+    /// Writes `scored` as a row of [`SCORED_CSV_HEADER`].
+    pub fn write_scored(&mut self, scored: &ScoredText) -> Result<(), std::io::Error> {
+        let spam = scored.prediction.spam.to_string();
+        let spam_probability = scored.prediction.spam_probability.to_string();
+        self.write_record([
+            scored.text.as_str(),
+            spam.as_str(),
+            spam_probability.as_str(),
+        ])
+    }
+
+    pub fn flush(&mut self) -> Result<(), std::io::Error> {
+        self.output.flush()
+    }
+
+    pub fn into_inner(self) -> W {
+        self.output
+    }
+}
+
+/// Layout of the rows written by [`score_file`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OutputFormat {
+    /// `sms,spam,spam_probability` with a header row, see [`CsvWriter`].
+    Csv,
+    /// One `{"sms": .., "spam": .., "spam_probability": ..}` object per line.
+    JsonLines,
+}
+
+/// Classifies every non-empty line of an unlabeled file, see [`score_reader`].
+pub fn score_file<P, W>(
+    classifier: &Classifier,
+    input: P,
+    output: W,
+    format: OutputFormat,
+) -> Result<usize, std::io::Error>
+where
+    P: AsRef<Path>,
+    W: Write,
+{
+    score_reader(
+        classifier,
+        BufReader::new(File::open(input)?),
+        output,
+        format,
+    )
+}
+
+/// Classifies `input` line by line, writing each scored row to `output` as soon as
+/// it is computed. Returns the number of rows written, not counting a header.
+pub fn score_reader<R, W>(
+    classifier: &Classifier,
+    input: R,
+    mut output: W,
+    format: OutputFormat,
+) -> Result<usize, std::io::Error>
+where
+    R: BufRead,
+    W: Write,
+{
+    let mut rows = 0;
+    match format {
+        OutputFormat::Csv => {
+            let mut writer = CsvWriter::new(&mut output);
+            writer.write_record(SCORED_CSV_HEADER)?;
+            for scored in classifier.predict_stream(input) {
+                writer.write_scored(&scored?)?;
+                rows += 1;
+            }
+        }
+        OutputFormat::JsonLines => {
+            for scored in classifier.predict_stream(input) {
+                let scored = scored?;
+                let row = serde_json::json!({
+                    "sms": scored.text,
+                    "spam": scored.prediction.spam,
+                    "spam_probability": scored.prediction.spam_probability,
+                });
+                writeln!(output, "{}", row)?;
+                rows += 1;
+            }
+        }
+    }
+    output.flush()?;
+    Ok(rows)
+}
+
+#[cfg(test)]
+mod test {
+    use std::io::Cursor;
+
+    use crate::fixtures;
+
+    use super::{score_reader, CsvWriter, OutputFormat};
+
+    #[test]
+    fn test_predict_file_stream() {
+        let path = std::env::temp_dir().join("sms-data-clean-predict-stream.txt");
+        std::fs::write(&path, INPUT).expect("failed to write input");
+
+        let classifier = fixtures::classifier();
+        let scored = classifier
+            .predict_file_stream(&path)
+            .expect("failed to open")
+            .collect::<Result<Vec<_>, _>>()
+            .expect("failed to score");
+
+        assert_eq!(scored.len(), 3);
+        assert_eq!(scored[0].text, "claim your free prize");
+        assert!(scored[0].prediction.spam);
+        assert_eq!(scored[1].text, "see you at lunch");
+        assert!(!scored[1].prediction.spam);
+    }
+
+    const INPUT: &[u8] = b"claim your free prize\n\nsee you at lunch\nwin cash now\n";
+
+    #[test]
+    fn test_score_json_lines() {
+        let mut output = Vec::new();
+        let rows = score_reader(
+            &fixtures::classifier(),
+            Cursor::new(INPUT),
+            &mut output,
+            OutputFormat::JsonLines,
+        )
+        .expect("failed to score");
+
+        assert_eq!(rows, 3);
+        let lines = String::from_utf8(output).unwrap();
+        let values = lines
+            .lines()
+            .map(|line| serde_json::from_str::<serde_json::Value>(line).unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(values.len(), 3);
+        for value in &values {
+            assert!(value["sms"].is_string());
+            assert!(value["spam"].is_boolean());
+            assert!(value["spam_probability"].is_f64());
+        }
+        assert_eq!(values[0]["sms"], "claim your free prize");
+        assert_eq!(values[0]["spam"], true);
+        assert_eq!(values[1]["spam"], false);
+    }
+
+    #[test]
+    fn test_score_csv() {
+        let mut output = Vec::new();
+        let rows = score_reader(
+            &fixtures::classifier(),
+            Cursor::new(INPUT),
+            &mut output,
+            OutputFormat::Csv,
+        )
+        .expect("failed to score");
+
+        assert_eq!(rows, 3);
+        let csv = String::from_utf8(output).unwrap();
+        let lines = csv.lines().collect::<Vec<_>>();
+        assert_eq!(lines.len(), 4);
+        assert_eq!(lines[0], "sms,spam,spam_probability");
+        assert!(lines[1].starts_with("claim your free prize,true,"));
+    }
+
+    /// Records of an RFC 4180 document.
+    fn parse_csv(csv: &str) -> Vec<Vec<String>> {
+        let (mut records, mut record, mut field) = (Vec::new(), Vec::new(), String::new());
+        let (mut quoted, mut chars) = (false, csv.chars().peekable());
+        while let Some(c) = chars.next() {
+            match (quoted, c) {
+                (true, '"') if chars.peek() == Some(&'"') => {
+                    chars.next();
+                    field.push('"');
+                }
+                (true, '"') => quoted = false,
+                (true, c) => field.push(c),
+                (false, '"') => quoted = true,
+                (false, ',') => record.push(std::mem::take(&mut field)),
+                (false, '\n') => {
+                    record.push(std::mem::take(&mut field));
+                    records.push(std::mem::take(&mut record));
+                }
+                (false, c) => field.push(c),
+            }
+        }
+        records
+    }
+
+    #[test]
+    fn test_score_csv_quoting() {
+        let input = "free prize, claim \"now\", call 0800\nlunch at 1,30?\nok\n";
+        let mut output = Vec::new();
+        let rows = score_reader(
+            &fixtures::classifier(),
+            Cursor::new(input),
+            &mut output,
+            OutputFormat::Csv,
+        )
+        .expect("failed to score");
+        assert_eq!(rows, 3);
+
+        let records = parse_csv(&String::from_utf8(output).unwrap());
+        assert_eq!(records.len(), 4);
+        assert_eq!(records[0], vec!["sms", "spam", "spam_probability"]);
+        assert!(records.iter().all(|record| record.len() == 3));
+        assert_eq!(records[1][0], "free prize, claim \"now\", call 0800");
+        assert_eq!(records[1][1], "true");
+        assert!(records[1][2].parse::<f64>().is_ok());
+        assert_eq!(records[2][0], "lunch at 1,30?");
+        assert_eq!(records[3][0], "ok");
+
+        let mut writer = CsvWriter::new(Vec::new());
+        writer
+            .write_record(["two\nlines", "plain", "", "\"quoted\""])
+            .unwrap();
+        let csv = String::from_utf8(writer.into_inner()).unwrap();
+        assert_eq!(csv, "\"two\nlines\",plain,,\"\"\"quoted\"\"\"\n");
+        assert_eq!(
+            parse_csv(&csv),
+            vec![vec!["two\nlines", "plain", "", "\"quoted\""]]
+        );
+    }
+}
@@ -0,0 +1,301 @@
+use serde::{Deserialize, Serialize};
+
+/// Platt scaling: maps a raw classifier score `f` to `1 / (1 + exp(a * f + b))`.
+///
+/// Naive Bayes log-odds are pushed towards the extremes, fitting `a` and `b` on
+/// held-out data turns them into probabilities that can be thresholded.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct PlattScaling {
+    pub a: f64,
+    pub b: f64,
+    /// Fraction of positives in the data [`PlattScaling::fit`] saw, with one pseudo
+    /// count per class, i.e. the prior the probabilities are calibrated to. `None`
+    /// when not fitted.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub base_rate: Option<f64>,
+}
+
+impl Default for PlattScaling {
+    /// The plain logistic function, i.e. no calibration at all.
+    fn default() -> Self {
+        Self {
+            a: -1.0,
+            b: 0.0,
+            base_rate: None,
+        }
+    }
+}
+
+impl PlattScaling {
+    /// Fits the sigmoid parameters by Newton's method with backtracking line search,
+    /// following Lin, Lin and Weng, "A note on Platt's probabilistic outputs for
+    /// support vector machines" (2007).
+    pub fn fit(scores: &[f64], labels: &[bool]) -> Self {
+        const MAX_ITER: usize = 100;
+        const MIN_STEP: f64 = 1e-10;
+        const SIGMA: f64 = 1e-12;
+
+        let positives = labels.iter().filter(|label| **label).count() as f64;
+        let negatives = labels.len() as f64 - positives;
+        let hi_target = (positives + 1.0) / (positives + 2.0);
+        let lo_target = 1.0 / (negatives + 2.0);
+        let targets = labels
+            .iter()
+            .map(|label| if *label { hi_target } else { lo_target })
+            .collect::<Vec<_>>();
+
+        let mut a = 0.0;
+        let mut b = ((negatives + 1.0) / (positives + 1.0)).ln();
+        let mut fval = objective(scores, &targets, a, b);
+
+        for _ in 0..MAX_ITER {
+            let (mut h11, mut h22, mut h21, mut g1, mut g2) = (SIGMA, SIGMA, 0.0, 0.0, 0.0);
+            for (score, target) in scores.iter().zip(&targets) {
+                let fapb = score * a + b;
+                let (p, q) = if fapb >= 0.0 {
+                    let e = (-fapb).exp();
+                    (e / (1.0 + e), 1.0 / (1.0 + e))
+                } else {
+                    let e = fapb.exp();
+                    (1.0 / (1.0 + e), e / (1.0 + e))
+                };
+                let d2 = p * q;
+                h11 += score * score * d2;
+                h22 += d2;
+                h21 += score * d2;
+                let d1 = target - p;
+                g1 += score * d1;
+                g2 += d1;
+            }
+            if g1.abs() < 1e-5 && g2.abs() < 1e-5 {
+                break;
+            }
+
+            let det = h11 * h22 - h21 * h21;
+            let da = -(h22 * g1 - h21 * g2) / det;
+            let db = -(-h21 * g1 + h11 * g2) / det;
+            let gd = g1 * da + g2 * db;
+
+            let mut step = 1.0;
+            while step >= MIN_STEP {
+                let (new_a, new_b) = (a + step * da, b + step * db);
+                let new_fval = objective(scores, &targets, new_a, new_b);
+                if new_fval < fval + 0.0001 * step * gd {
+                    a = new_a;
+                    b = new_b;
+                    fval = new_fval;
+                    break;
+                }
+                step /= 2.0;
+            }
+            if step < MIN_STEP {
+                break;
+            }
+        }
+
+        Self {
+            a,
+            b,
+            base_rate: Some((positives + 1.0) / (labels.len() as f64 + 2.0)),
+        }
+    }
+
+    pub fn probability(&self, score: f64) -> f64 {
+        sigmoid(self.logit(score))
+    }
+
+    /// Log-odds of [`PlattScaling::probability`], `-(a * score + b)`.
+    pub fn logit(&self, score: f64) -> f64 {
+        -(score * self.a + self.b)
+    }
+}
+
+/// `1 / (1 + exp(-x))`, without overflowing for large `|x|`.
+pub(crate) fn sigmoid(x: f64) -> f64 {
+    if x <= 0.0 {
+        let e = x.exp();
+        e / (1.0 + e)
+    } else {
+        1.0 / (1.0 + (-x).exp())
+    }
+}
+
+/// Negative log-likelihood of the targets under the sigmoid `(a, b)`.
+fn objective(scores: &[f64], targets: &[f64], a: f64, b: f64) -> f64 {
+    scores
+        .iter()
+        .zip(targets)
+        .map(|(score, target)| {
+            let fapb = score * a + b;
+            if fapb >= 0.0 {
+                target * fapb + (-fapb).exp().ln_1p()
+            } else {
+                (target - 1.0) * fapb + fapb.exp().ln_1p()
+            }
+        })
+        .sum()
+}
+
+/// Mean squared difference between predicted probabilities and the 0/1 outcome.
+pub fn brier_score(probabilities: &[f64], labels: &[bool]) -> f64 {
+    let total = probabilities
+        .iter()
+        .zip(labels)
+        .map(|(p, label)| {
+            let outcome = if *label { 1.0 } else { 0.0 };
+            (p - outcome).powi(2)
+        })
+        .sum::<f64>();
+    total / probabilities.len() as f64
+}
+
+/// Predictions whose probability falls into `[lower, upper)`, the last bin also
+/// includes `1`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ReliabilityBin {
+    pub lower: f64,
+    pub upper: f64,
+    pub count: usize,
+    /// Mean predicted probability, `0` for an empty bin.
+    pub mean_probability: f64,
+    /// Fraction of positive labels, `0` for an empty bin.
+    pub positive_rate: f64,
+}
+
+/// Result of [`expected_calibration_error`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct CalibrationReport {
+    pub ece: f64,
+    pub bins: Vec<ReliabilityBin>,
+}
+
+/// Expected calibration error over `bins` equal-width probability bins: the mean
+/// over all predictions of `|positive rate - mean probability|` of their bin, zero
+/// when there are no predictions.
+pub fn expected_calibration_error(
+    probabilities: &[f64],
+    labels: &[bool],
+    bins: usize,
+) -> CalibrationReport {
+    let bins = bins.max(1);
+    let mut sums = vec![(0usize, 0.0, 0usize); bins];
+    for (p, label) in probabilities.iter().zip(labels) {
+        let bin = ((p * bins as f64) as usize).min(bins - 1);
+        let (count, probability, positives) = &mut sums[bin];
+        *count += 1;
+        *probability += p;
+        if *label {
+            *positives += 1;
+        }
+    }
+
+    let total = sums.iter().map(|(count, _, _)| count).sum::<usize>() as f64;
+    let mut ece = 0.0;
+    let bins = sums
+        .into_iter()
+        .enumerate()
+        .map(|(index, (count, probability, positives))| {
+            let (mean_probability, positive_rate) = if count > 0 {
+                let mean_probability = probability / count as f64;
+                let positive_rate = positives as f64 / count as f64;
+                ece += count as f64 / total * (positive_rate - mean_probability).abs();
+                (mean_probability, positive_rate)
+            } else {
+                (0.0, 0.0)
+            };
+            ReliabilityBin {
+                lower: index as f64 / bins as f64,
+                upper: (index + 1) as f64 / bins as f64,
+                count,
+                mean_probability,
+                positive_rate,
+            }
+        })
+        .collect();
+    CalibrationReport { ece, bins }
+}
+
+#[cfg(test)]
+mod test {
+    use smartcore::model_selection::train_test_split;
+    use smartcore::naive_bayes::multinomial::MultinomialNB;
+
+    use crate::{create_smartcore_input, matrix_rows, spam_log_odds};
+
+    use super::{brier_score, expected_calibration_error, PlattScaling};
+
+    #[test]
+    fn test_fit_recovers_sigmoid() {
+        let scores = (-50..=50).map(|i| i as f64 / 10.0).collect::<Vec<_>>();
+        let labels = scores
+            .iter()
+            .enumerate()
+            .map(|(i, score)| (i % 7) as f64 / 7.0 < 1.0 / (1.0 + (-2.0 * score).exp()))
+            .collect::<Vec<_>>();
+        let platt = PlattScaling::fit(&scores, &labels);
+        assert!(platt.a < 0.0);
+        assert!(platt.probability(3.0) > 0.9);
+        assert!(platt.probability(-3.0) < 0.1);
+    }
+
+    #[test]
+    fn test_calibration_improves_brier_score() {
+        let (x, y, _) =
+            create_smartcore_input::<usize, _>("../../SMSSpamCollection").expect("failed to init");
+        let (x_train, x_validation, y_train, y_validation) =
+            train_test_split(&x, &y, 0.3, true, Some(10));
+        let model =
+            MultinomialNB::fit(&x_train, &y_train, Default::default()).expect("failed to fit");
+
+        let scores = matrix_rows(&x_validation)
+            .iter()
+            .map(|row| spam_log_odds(&model, row))
+            .collect::<Vec<_>>();
+        let labels = y_validation.iter().map(|y| *y == 1).collect::<Vec<_>>();
+
+        let uncalibrated = PlattScaling::default();
+        let calibrated = PlattScaling::fit(&scores, &labels);
+        let probabilities = |platt: PlattScaling| {
+            scores
+                .iter()
+                .map(|score| platt.probability(*score))
+                .collect::<Vec<_>>()
+        };
+
+        let before = brier_score(&probabilities(uncalibrated), &labels);
+        let after = brier_score(&probabilities(calibrated), &labels);
+        assert!(after <= before, "brier score {after} > {before}");
+    }
+
+    #[test]
+    fn test_expected_calibration_error() {
+        let mut probabilities = Vec::new();
+        let mut labels = Vec::new();
+        for bin in 0..10 {
+            let p = bin as f64 / 10.0 + 0.05;
+            let positives = (p * 100.0).round() as usize;
+            for i in 0..100 {
+                probabilities.push(p);
+                labels.push(i < positives);
+            }
+        }
+
+        let calibrated = expected_calibration_error(&probabilities, &labels, 10);
+        assert!(calibrated.ece < 1e-9, "ece {}", calibrated.ece);
+        assert_eq!(calibrated.bins.len(), 10);
+        assert!(calibrated.bins.iter().all(|bin| bin.count == 100));
+        assert!((calibrated.bins[3].positive_rate - 0.35).abs() < 1e-9);
+
+        let overconfident = probabilities
+            .iter()
+            .map(|p| if *p > 0.5 { 0.99 } else { 0.01 })
+            .collect::<Vec<_>>();
+        let miscalibrated = expected_calibration_error(&overconfident, &labels, 10);
+        assert!(miscalibrated.ece > 0.2, "ece {}", miscalibrated.ece);
+        assert_eq!(miscalibrated.bins[0].count, 500);
+
+        let empty = expected_calibration_error(&[], &[], 10);
+        assert_eq!(empty.ece, 0.0);
+        assert!(empty.bins.iter().all(|bin| bin.count == 0));
+    }
+}
@@ -0,0 +1,504 @@
+use smartcore::error::Failed;
+
+use crate::calibration::sigmoid;
+use crate::{
+    bag_of_words_iter, KeywordRules, Label, LogOddsWeights, NaiveBayesModel, PlattScaling,
+    Tokenizer, Vocabulary,
+};
+
+/// Verdict for a single message.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Prediction {
+    pub spam: bool,
+    /// Calibrated probability that the message is spam, `1` or `0` for rule verdicts.
+    pub spam_probability: f64,
+    pub decided_by: Decision,
+}
+
+/// What produced a [`Prediction`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Decision {
+    Model,
+    /// A [`KeywordRules::blocklist`] keyword matched.
+    Blocklist,
+    /// A [`KeywordRules::allowlist`] keyword matched.
+    Allowlist,
+}
+
+impl std::fmt::Display for Decision {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let decision = match self {
+            Decision::Model => "model",
+            Decision::Blocklist => "blocklist",
+            Decision::Allowlist => "allowlist",
+        };
+        f.pad(decision)
+    }
+}
+
+/// Anything that can score a raw message.
+pub trait TextClassifier {
+    fn predict(&self, text: &str) -> Result<Prediction, Failed>;
+}
+
+/// Trained model together with everything needed to score raw text.
+///
+/// The verdict is the class with the highest joint log-likelihood. When both are
+/// exactly equal, e.g. for a message without known tokens and a model trained on
+/// balanced classes, the tie-break label wins, [`Label::Ham`] unless configured
+/// otherwise with [`Classifier::with_tie_break`].
+///
+/// The spam prior learned from the training corpus can be replaced with the base rate
+/// of the deployment with [`Classifier::with_spam_prior`], without retraining.
+///
+/// [`KeywordRules`] set with [`Classifier::with_rules`] are checked before the model.
+/// Messages are split with [`Tokenizer::standard`], the tokenization of the bundled
+/// model, unless configured otherwise with [`Classifier::with_tokenizer`].
+#[derive(Debug)]
+pub struct Classifier {
+    model: NaiveBayesModel,
+    vocabulary: Vocabulary,
+    calibration: PlattScaling,
+    tie_break: Label,
+    rules: KeywordRules,
+    tokenizer: Tokenizer,
+    /// The model precomputed for scoring, so each message costs a dot product.
+    weights: LogOddsWeights,
+    /// Added to the log-odds of the model to swap its prior for another one.
+    prior_shift: f64,
+    /// Added to the calibrated log-odds to swap the base rate of the calibration for
+    /// the same prior.
+    calibrated_shift: f64,
+}
+
+impl Classifier {
+    /// Fails when the model was not trained on as many features as the vocabulary
+    /// has tokens, e.g. when artifacts of different training runs are mixed up.
+    pub fn new(
+        model: NaiveBayesModel,
+        vocabulary: Vocabulary,
+        calibration: PlattScaling,
+    ) -> Result<Self, std::io::Error> {
+        if model.n_features() != vocabulary.len() {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                format!(
+                    "model expects {} features but the vocabulary has {} tokens",
+                    model.n_features(),
+                    vocabulary.len()
+                ),
+            ));
+        }
+        Ok(Self {
+            weights: LogOddsWeights::new(&model),
+            model,
+            vocabulary,
+            calibration,
+            tie_break: Label::Ham,
+            rules: KeywordRules::default(),
+            tokenizer: Tokenizer::standard(),
+            prior_shift: 0.0,
+            calibrated_shift: 0.0,
+        })
+    }
+
+    /// Must match the tokenization the model was trained with.
+    pub fn with_tokenizer(mut self, tokenizer: Tokenizer) -> Self {
+        self.tokenizer = tokenizer;
+        self
+    }
+
+    pub fn with_rules(mut self, rules: KeywordRules) -> Self {
+        self.rules = rules;
+        self
+    }
+
+    /// Scores messages as if a fraction `spam_prior` of all messages were spam,
+    /// instead of the fraction seen in training.
+    ///
+    /// The verdict moves the model log-odds from the training prior to `spam_prior`.
+    /// The probability moves the calibrated log-odds from the
+    /// [`PlattScaling::base_rate`] the calibration was fitted on, or from the training
+    /// prior for an unfitted one. Fails unless `spam_prior` is strictly between `0`
+    /// and `1`.
+    pub fn with_spam_prior(mut self, spam_prior: f64) -> Result<Self, std::io::Error> {
+        if !(spam_prior > 0.0 && spam_prior < 1.0) {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                format!("spam prior {spam_prior} is not strictly between 0 and 1"),
+            ));
+        }
+        let log_odds = |p: f64| (p / (1.0 - p)).ln();
+        self.prior_shift = log_odds(spam_prior) - self.weights.bias;
+        self.calibrated_shift = log_odds(spam_prior)
+            - self
+                .calibration
+                .base_rate
+                .map_or(self.weights.bias, log_odds);
+        Ok(self)
+    }
+
+    pub fn with_tie_break(mut self, tie_break: Label) -> Self {
+        self.tie_break = tie_break;
+        self
+    }
+
+    pub fn model(&self) -> &NaiveBayesModel {
+        &self.model
+    }
+
+    pub fn vocabulary(&self) -> &Vocabulary {
+        &self.vocabulary
+    }
+
+    pub fn tokenizer(&self) -> &Tokenizer {
+        &self.tokenizer
+    }
+
+    pub fn rules(&self) -> &KeywordRules {
+        &self.rules
+    }
+
+    /// Splits a message into the tokens looked up in the vocabulary.
+    pub fn tokenize(&self, text: &str) -> Vec<String> {
+        self.tokenizer.tokenize(text)
+    }
+
+    /// Fraction of the tokens of `text` that are in the vocabulary, `0` for a message
+    /// without tokens. The lower it is, the less evidence a verdict rests on.
+    pub fn coverage(&self, text: &str) -> f64 {
+        self.token_coverage(&self.tokenize(text))
+    }
+
+    /// Like [`Classifier::coverage`] for the tokens of an already tokenized message.
+    pub fn token_coverage<S: AsRef<str>>(&self, tokens: &[S]) -> f64 {
+        if tokens.is_empty() {
+            return 0.0;
+        }
+        let known = tokens
+            .iter()
+            .filter(|token| self.vocabulary.get(token.as_ref()).is_some())
+            .count();
+        known as f64 / tokens.len() as f64
+    }
+
+    pub fn features(&self, text: &str) -> Vec<usize> {
+        let text = self.tokenizer.clean(text);
+        bag_of_words_iter(self.tokenizer.tokens(&text), &self.vocabulary)
+    }
+
+    /// Like [`Classifier::features`] for the tokens of an already tokenized message,
+    /// e.g. to check them before predicting without tokenizing twice.
+    pub fn token_features<S: AsRef<str>>(&self, tokens: &[S]) -> Vec<usize> {
+        bag_of_words_iter(tokens, &self.vocabulary)
+    }
+
+    pub fn predict(&self, text: &str) -> Result<Prediction, Failed> {
+        if let Some(prediction) = self.rules.decide(text) {
+            return Ok(prediction);
+        }
+        Ok(self.predict_features(&self.features(text)))
+    }
+
+    /// Predicts an already vectorized message with the model alone.
+    pub fn predict_features(&self, x: &[usize]) -> Prediction {
+        let log_odds = self.weights.log_odds(x);
+        let shifted = log_odds + self.prior_shift;
+        let spam = if shifted == 0.0 {
+            self.tie_break == Label::Spam
+        } else {
+            shifted > 0.0
+        };
+        Prediction {
+            spam,
+            spam_probability: sigmoid(self.calibration.logit(log_odds) + self.calibrated_shift),
+            decided_by: Decision::Model,
+        }
+    }
+
+    /// Predicts `text` and lists its tokens by how much they pushed the verdict,
+    /// `count * (ln P(token | predicted) - ln P(token | other))`, strongest first.
+    pub fn predict_explain(&self, text: &str) -> Result<(Prediction, Vec<(String, f64)>), Failed> {
+        let prediction = self.predict(text)?;
+        let (predicted, other) = if prediction.spam { (1, 0) } else { (0, 1) };
+        let (predicted, other) = match (self.class_index(predicted), self.class_index(other)) {
+            (Some(predicted), Some(other)) => (predicted, other),
+            _ => return Ok((prediction, Vec::new())),
+        };
+        let log_probs = self.model.feature_log_prob();
+        let x = self.features(text);
+
+        let mut tokens = self.tokenize(text);
+        tokens.sort();
+        tokens.dedup();
+        let mut contributions = tokens
+            .into_iter()
+            .filter_map(|token| {
+                let index = self.vocabulary.get(&token)?;
+                let weight = log_probs[predicted][index] - log_probs[other][index];
+                Some((token, x[index] as f64 * weight))
+            })
+            .collect::<Vec<_>>();
+        contributions.sort_by(|a, b| b.1.total_cmp(&a.1));
+        Ok((prediction, contributions))
+    }
+
+    /// Approximates the fewest vocabulary tokens that, appended once each to a
+    /// message the model scores as ham, would flip its verdict to spam. Tokens are
+    /// picked greedily by `ln P(token | spam) - ln P(token | ham)`, strongest first.
+    ///
+    /// Empty when the model already scores the message as spam, or when not even
+    /// every spam-leaning token in the vocabulary would cross the decision boundary.
+    pub fn minimal_flip_tokens(&self, text: &str) -> Vec<String> {
+        let x = self.features(text);
+        if self.predict_features(&x).spam {
+            return Vec::new();
+        }
+        let mut names = vec![""; self.vocabulary.len()];
+        for (token, index) in self.vocabulary.iter() {
+            names[index] = token;
+        }
+        let mut candidates = (0..names.len())
+            .map(|index| (index, self.weights.weights[index]))
+            .filter(|(_, weight)| *weight > 0.0)
+            .collect::<Vec<_>>();
+        candidates.sort_by(|a, b| b.1.total_cmp(&a.1));
+
+        let mut log_odds = self.weights.log_odds(&x) + self.prior_shift;
+        let mut tokens = Vec::new();
+        for (index, weight) in candidates {
+            log_odds += weight;
+            tokens.push(names[index].to_string());
+            if log_odds > 0.0 || (log_odds == 0.0 && self.tie_break == Label::Spam) {
+                return tokens;
+            }
+        }
+        Vec::new()
+    }
+
+    /// Position of `class` in the rows of the model's tables.
+    fn class_index(&self, class: usize) -> Option<usize> {
+        self.model
+            .classes()
+            .iter()
+            .position(|label| *label == class)
+    }
+}
+
+impl TextClassifier for Classifier {
+    fn predict(&self, text: &str) -> Result<Prediction, Failed> {
+        Classifier::predict(self, text)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::codegen::train;
+    use crate::{
+        bag_of_words_iter, fixtures, retrain_with_extended_vocabulary, spam_log_odds, KeywordRules,
+        Label, PlattScaling, Tokenizer, Vocabulary,
+    };
+
+    use super::{Classifier, Decision};
+
+    #[test]
+    fn test_tie_break() {
+        let dataset =
+            fixtures::dataset(&[(Label::Spam, "free prize"), (Label::Ham, "lunch later")]);
+        let (model, vocabulary) =
+            retrain_with_extended_vocabulary(Vocabulary::new(), dataset).expect("failed to train");
+        let classifier =
+            Classifier::new(model, vocabulary, PlattScaling::default()).expect("invalid model");
+        let x = vec![0; classifier.vocabulary().len()];
+
+        let prediction = classifier.predict_features(&x);
+        assert!(!prediction.spam);
+        assert_eq!(prediction.spam_probability, 0.5);
+        assert_eq!(classifier.predict("").unwrap(), prediction);
+
+        let classifier = classifier.with_tie_break(Label::Spam);
+        assert!(classifier.predict_features(&x).spam);
+    }
+
+    #[test]
+    fn test_keyword_rules_override_model() {
+        let classifier = fixtures::classifier().with_rules(KeywordRules {
+            blocklist: vec!["bad.example.com".to_string()],
+            allowlist: vec!["your free prize from the library".to_string()],
+        });
+
+        let lunch = classifier
+            .predict("see you at lunch, menu at http://BAD.example.com")
+            .unwrap();
+        assert!(lunch.spam);
+        assert_eq!(lunch.decided_by, Decision::Blocklist);
+
+        let prize = classifier
+            .predict("Claim your free prize from the library")
+            .unwrap();
+        assert!(!prize.spam);
+        assert_eq!(prize.decided_by, Decision::Allowlist);
+
+        let model = classifier.predict("claim your free prize").unwrap();
+        assert!(model.spam);
+        assert_eq!(model.decided_by, Decision::Model);
+    }
+
+    #[test]
+    fn test_coverage() {
+        let classifier = fixtures::classifier();
+        assert_eq!(classifier.coverage(""), 0.0);
+        assert_eq!(classifier.coverage("free prize"), 1.0);
+        assert_eq!(classifier.coverage("free prize zorblax quuxify"), 0.5);
+        assert_eq!(classifier.token_coverage(&["free", "zorblax"]), 0.5);
+    }
+
+    #[test]
+    fn test_features_match_tokens() {
+        let classifier =
+            fixtures::classifier().with_tokenizer(Tokenizer::standard().with_max_tokens(3));
+        let text = "Claim your FREE prize, free cash at lunch!";
+        assert_eq!(
+            classifier.features(text),
+            bag_of_words_iter::<usize, _>(classifier.tokenize(text), classifier.vocabulary())
+        );
+        assert_eq!(
+            classifier.features(text),
+            classifier.token_features(&classifier.tokenize(text))
+        );
+    }
+
+    #[test]
+    fn test_spam_prior() {
+        let messages = [
+            "see you at lunch",
+            "call me later",
+            "lunch",
+            "claim your free prize",
+        ];
+        let spam_count = |classifier: &Classifier| {
+            messages
+                .iter()
+                .filter(|text| classifier.predict(text).unwrap().spam)
+                .count()
+        };
+
+        let default = spam_count(&fixtures::classifier());
+        let high = spam_count(&fixtures::classifier().with_spam_prior(0.95).unwrap());
+        let low = spam_count(&fixtures::classifier().with_spam_prior(0.05).unwrap());
+        assert!(high > default, "{} <= {}", high, default);
+        assert!(low <= default, "{} > {}", low, default);
+
+        let trained = fixtures::classifier();
+        let matching = fixtures::classifier().with_spam_prior(3.0 / 7.0).unwrap();
+        let x = trained.features("free lunch");
+        let difference = trained.predict_features(&x).spam_probability
+            - matching.predict_features(&x).spam_probability;
+        assert!(difference.abs() < 1e-12);
+
+        for spam_prior in [0.0, 1.0, -0.5, f64::NAN] {
+            let err = fixtures::classifier()
+                .with_spam_prior(spam_prior)
+                .unwrap_err();
+            assert_eq!(err.kind(), std::io::ErrorKind::InvalidInput);
+        }
+    }
+
+    #[test]
+    fn test_spam_prior_with_fitted_calibration() {
+        let classifier = |calibration| {
+            let dataset = fixtures::raw_dataset(fixtures::TRAINING_ROWS)
+                .tokenize_with(&Tokenizer::standard());
+            let (model, vocabulary) = retrain_with_extended_vocabulary(Vocabulary::new(), dataset)
+                .expect("failed to train");
+            Classifier::new(model, vocabulary, calibration).expect("invalid model")
+        };
+        let held_out = [
+            ("free prize", true),
+            ("win cash now", true),
+            ("lunch later", false),
+            ("call me at home", false),
+            ("free lunch", false),
+            ("see you", false),
+        ];
+        let uncalibrated = classifier(PlattScaling::default());
+        let scores = held_out
+            .iter()
+            .map(|(text, _)| spam_log_odds(uncalibrated.model(), &uncalibrated.features(text)))
+            .collect::<Vec<_>>();
+        let labels = held_out.iter().map(|(_, spam)| *spam).collect::<Vec<_>>();
+        let calibration = PlattScaling::fit(&scores, &labels);
+        assert_eq!(calibration.base_rate, Some(3.0 / 8.0));
+
+        let probability = |classifier: &Classifier| {
+            let x = classifier.features("free lunch");
+            classifier.predict_features(&x).spam_probability
+        };
+        let calibrated = probability(&classifier(calibration));
+        // The calibration already matches this prior, shifting by the training prior
+        // instead would move the probability.
+        let matching = probability(&classifier(calibration).with_spam_prior(3.0 / 8.0).unwrap());
+        assert!((matching - calibrated).abs() < 1e-12);
+        let high = probability(&classifier(calibration).with_spam_prior(0.9).unwrap());
+        let low = probability(&classifier(calibration).with_spam_prior(0.1).unwrap());
+        assert!(high > calibrated && calibrated > low);
+        let odds = |p: f64| p / (1.0 - p);
+        let expected = odds(calibrated) * odds(0.9) / odds(3.0 / 8.0);
+        assert!((odds(high) - expected).abs() < 1e-9 * expected);
+    }
+
+    #[test]
+    fn test_minimal_flip_tokens() {
+        let classifier = fixtures::classifier();
+        let text = "lunch later";
+        assert!(!classifier.predict(text).unwrap().spam);
+
+        let tokens = classifier.minimal_flip_tokens(text);
+        assert_eq!(tokens[0], "free");
+        let flipped = format!("{} {}", text, tokens.join(" "));
+        assert!(classifier.predict(&flipped).unwrap().spam);
+        let short = format!("{} {}", text, tokens[..tokens.len() - 1].join(" "));
+        assert!(!classifier.predict(&short).unwrap().spam);
+
+        assert!(classifier
+            .minimal_flip_tokens("claim your free prize")
+            .is_empty());
+    }
+
+    #[test]
+    fn test_mismatched_vocabulary() {
+        let dataset =
+            fixtures::dataset(&[(Label::Spam, "free prize"), (Label::Ham, "lunch later")]);
+        let (model, mut vocabulary) =
+            retrain_with_extended_vocabulary(Vocabulary::new(), dataset).expect("failed to train");
+        vocabulary.insert("unseen");
+
+        let err = Classifier::new(model, vocabulary, PlattScaling::default()).unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+        assert!(err.to_string().contains("4 features"), "{}", err);
+    }
+
+    #[test]
+    fn test_predict_explain() {
+        let trained = train("../../SMSSpamCollection").expect("failed to train");
+        let classifier = Classifier::new(trained.model, trained.vocabulary, trained.calibration)
+            .expect("invalid model");
+
+        let (prediction, contributions) = classifier
+            .predict_explain("congratulations you have won a free prize call now to claim")
+            .expect("failed to predict");
+
+        assert!(prediction.spam);
+        let top = contributions
+            .iter()
+            .take(3)
+            .map(|(token, _)| token.as_str())
+            .collect::<Vec<_>>();
+        assert!(
+            top.iter()
+                .any(|token| ["prize", "claim", "free", "won"].contains(token)),
+            "unexpected top contributors {top:?}"
+        );
+        assert!(contributions.windows(2).all(|pair| pair[0].1 >= pair[1].1));
+    }
+}
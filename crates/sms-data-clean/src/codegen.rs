@@ -0,0 +1,428 @@
+use std::ffi::OsStr;
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Write};
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+use smartcore::metrics::accuracy::Accuracy;
+use smartcore::metrics::Metrics;
+use smartcore::naive_bayes::multinomial::MultinomialNB;
+
+use crate::{
+    create_smartcore_input, matrix_rows, spam_log_odds, stratified_split, Classifier,
+    ConfusionMatrix, LabelEncoder, NaiveBayesModel, PlattScaling, Vocabulary,
+};
+
+/// Artifacts produced by [`train`], ready to be embedded with [`model_module`].
+#[derive(Debug)]
+pub struct TrainedModel {
+    pub model: NaiveBayesModel,
+    pub vocabulary: Vocabulary,
+    pub calibration: PlattScaling,
+    /// Accuracy on the held-out split.
+    pub accuracy: f64,
+    /// Confusion matrix on the held-out split.
+    pub confusion: ConfusionMatrix,
+}
+
+/// What [`TrainedModel::save`] writes: everything a [`Classifier`] is built from.
+#[derive(Serialize, Deserialize)]
+struct ModelFile<M, V, C> {
+    model: M,
+    vocabulary: V,
+    calibration: C,
+}
+
+impl TrainedModel {
+    /// Writes the model, vocabulary and calibration as JSON, to classify with
+    /// [`load_classifier`] without training again.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), std::io::Error> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        let file = ModelFile {
+            model: &self.model,
+            vocabulary: &self.vocabulary,
+            calibration: &self.calibration,
+        };
+        serde_json::to_writer(&mut writer, &file)?;
+        writer.flush()
+    }
+}
+
+/// Reads a model written by [`TrainedModel::save`], with the
+/// [`Tokenizer::standard`](crate::Tokenizer::standard) tokenization it was trained
+/// with.
+pub fn load_classifier<P: AsRef<Path>>(path: P) -> Result<Classifier, std::io::Error> {
+    let file: ModelFile<NaiveBayesModel, Vocabulary, PlattScaling> =
+        serde_json::from_reader(BufReader::new(File::open(path)?))?;
+    Classifier::new(file.model, file.vocabulary, file.calibration)
+}
+
+/// Held-out accuracy below which the `spam-model` build script refuses the model.
+pub const MIN_ACCURACY: f64 = 0.9;
+
+/// Fails unless the held-out accuracy of `trained` exceeds `min_accuracy`, with an
+/// error reporting the accuracy, the confusion matrix and the likely causes.
+pub fn check_accuracy(trained: &TrainedModel, min_accuracy: f64) -> Result<(), std::io::Error> {
+    if trained.accuracy > min_accuracy {
+        return Ok(());
+    }
+    Err(std::io::Error::new(
+        std::io::ErrorKind::InvalidData,
+        format!(
+            "held-out accuracy {:.3} is not above the required {:.3}\n\n{}\n\
+             The dataset may be too small or too unbalanced to learn from, or \
+             preprocessing may differ between training and prediction.",
+            trained.accuracy, min_accuracy, trained.confusion
+        ),
+    ))
+}
+
+/// Seed of the train/test split of [`train`].
+pub const DEFAULT_SPLIT_SEED: u64 = 10;
+
+/// Environment variable the `spam-model` build script reads the split seed from,
+/// e.g. to check how stable the model is across splits.
+pub const SPLIT_SEED_VAR: &str = "SMS_SPLIT_SEED";
+
+/// Parses the value of [`SPLIT_SEED_VAR`], [`DEFAULT_SPLIT_SEED`] when unset.
+pub fn split_seed(value: Option<&OsStr>) -> Result<u64, std::io::Error> {
+    let value = match value {
+        Some(value) => value,
+        None => return Ok(DEFAULT_SPLIT_SEED),
+    };
+    value
+        .to_str()
+        .and_then(|value| value.trim().parse().ok())
+        .ok_or_else(|| {
+            std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                format!(
+                    "invalid {SPLIT_SEED_VAR} `{}`, expected a non-negative integer",
+                    value.to_string_lossy()
+                ),
+            )
+        })
+}
+
+/// Share of the training split held out to fit the calibration on, so that neither
+/// the model nor the reported accuracy sees those messages.
+pub const CALIBRATION_SIZE: f32 = 0.25;
+
+/// Trains the smartmodule model on a labeled SMS file.
+///
+/// The calibration is fitted on [`CALIBRATION_SIZE`] of the training split, which
+/// the model is not fitted on, and the accuracy is reported on the test split.
+/// Messages left without tokens after preprocessing are not trained on, see
+/// [`Dataset::drop_empty`](crate::Dataset::drop_empty).
+pub fn train<P: AsRef<Path>>(dataset: P) -> Result<TrainedModel, std::io::Error> {
+    train_with_seed(dataset, DEFAULT_SPLIT_SEED)
+}
+
+/// Like [`train`], shuffling the train/test and the calibration splits with `seed`.
+/// Both splits are stratified, see [`stratified_split`], so every class is fitted
+/// on whatever the seed.
+pub fn train_with_seed<P: AsRef<Path>>(
+    dataset: P,
+    seed: u64,
+) -> Result<TrainedModel, std::io::Error> {
+    let (x, y, vocabulary) = create_smartcore_input::<usize, _>(dataset)?;
+
+    let (x_train, x_test, y_train, y_test) = stratified_split(&x, &y, 0.7, seed)?;
+    if ((y_train.len() as f32) * CALIBRATION_SIZE) < 1.0 {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidData,
+            format!(
+                "{} training messages are too few to hold out a calibration split",
+                y_train.len()
+            ),
+        ));
+    }
+    let (x_fit, x_calibration, y_fit, y_calibration) =
+        stratified_split(&x_train, &y_train, CALIBRATION_SIZE, seed)?;
+
+    let model = MultinomialNB::fit(&x_fit, &y_fit, Default::default())
+        .map_err(|err| std::io::Error::new(std::io::ErrorKind::Other, err.to_string()))?;
+    let y_result = model
+        .predict(&x_test)
+        .map_err(|err| std::io::Error::new(std::io::ErrorKind::Other, err.to_string()))?;
+    let accuracy = Accuracy::new().get_score(&y_test, &y_result);
+    let encoder = LabelEncoder::default();
+    let decode = |y: &Vec<usize>| {
+        y.iter()
+            .map(|y| encoder.decode(*y).expect("labels are encoded as 0 or 1"))
+            .collect::<Vec<_>>()
+    };
+    let confusion = ConfusionMatrix::new(&decode(&y_test), &decode(&y_result));
+
+    let scores = matrix_rows(&x_calibration)
+        .iter()
+        .map(|row| spam_log_odds(&model, row))
+        .collect::<Vec<_>>();
+    let labels = y_calibration.iter().map(|y| *y == 1).collect::<Vec<_>>();
+    let calibration = PlattScaling::fit(&scores, &labels);
+
+    Ok(TrainedModel {
+        model,
+        vocabulary,
+        calibration,
+        accuracy,
+        confusion,
+    })
+}
+
+/// Rust source of the `model` module included by the smartmodules.
+pub fn model_module(trained: &TrainedModel) -> Result<String, serde_json::Error> {
+    let mut source = Vec::new();
+    write_model_module(trained, &mut source).map_err(serde_json::Error::io)?;
+    Ok(String::from_utf8(source).expect("generated source is valid UTF-8"))
+}
+
+/// Writes the `model` module to a file at `path`, e.g. to commit a pre-trained model
+/// into a source tree and include it with `mod model;` instead of training in a
+/// build script. The module needs `serde_json`, `smartcore` and `sms_data_clean`.
+pub fn generate_model_module<P: AsRef<Path>>(
+    trained: &TrainedModel,
+    path: P,
+) -> Result<(), std::io::Error> {
+    write_model_module(trained, BufWriter::new(File::create(path)?))
+}
+
+/// Writes the same source as [`model_module`] to `writer`, serializing the model
+/// straight into it instead of building the whole module in memory first.
+pub fn write_model_module<W: Write>(
+    trained: &TrainedModel,
+    mut writer: W,
+) -> Result<(), std::io::Error> {
+    write!(
+        writer,
+        "
+        use smartcore::linalg::basic::matrix::DenseMatrix;
+        use smartcore::naive_bayes::multinomial::MultinomialNB;
+        pub fn naive_bayes_model() -> MultinomialNB<usize, usize, DenseMatrix<usize>, Vec<usize>> {{
+           serde_json::from_str(r#\""
+    )?;
+    serde_json::to_writer(&mut writer, &trained.model)?;
+    write!(
+        writer,
+        "\"#).unwrap()
+        }}
+
+        pub fn vocabulary() -> sms_data_clean::Vocabulary {{
+            serde_json::from_str(r#\""
+    )?;
+    serde_json::to_writer(&mut writer, &trained.vocabulary)?;
+    write!(
+        writer,
+        "\"#).unwrap()
+        }}
+
+        pub fn calibration() -> sms_data_clean::PlattScaling {{
+            serde_json::from_str(r#\""
+    )?;
+    serde_json::to_writer(&mut writer, &trained.calibration)?;
+    write!(
+        writer,
+        "\"#).unwrap()
+        }}
+
+        pub fn classifier() -> sms_data_clean::Classifier {{
+            sms_data_clean::Classifier::new(naive_bayes_model(), vocabulary(), calibration())
+                .expect(\"bundled model and vocabulary disagree\")
+        }}
+    "
+    )?;
+    writer.flush()
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{Classifier, NaiveBayesModel, PlattScaling, Vocabulary};
+
+    use std::ffi::OsStr;
+
+    use super::{
+        check_accuracy, generate_model_module, load_classifier, model_module, split_seed, train,
+        train_with_seed, DEFAULT_SPLIT_SEED, MIN_ACCURACY,
+    };
+
+    // Every spam message has a few of `free`, `cash`, `prize` and `claim`, every ham
+    // message one of `see`, `lunch` and `tomorrow`, so that whichever rows a seed
+    // fits on, the model tells them apart.
+    const FIXTURE: &str = "\
+spam\tWIN a free prize, call now!
+ham\tAre we still meeting for lunch?
+spam\tFree entry to win cash, text WIN
+ham\tSee you at the office tomorrow
+spam\tClaim your free prize today
+ham\tSee you at lunch tomorrow
+spam\tYou won a cash prize, claim now
+ham\tCan we see the movie tomorrow
+spam\tFree cash prize waiting, call now
+ham\tLunch was great, see you tomorrow
+spam\tText WIN to claim your free entry
+ham\tGot home safe, talk tomorrow
+spam\tUrgent! Claim your cash reward
+ham\tRunning late for lunch, sorry
+spam\tFree prize draw, reply YES to claim
+ham\tLunch at noon works for me
+spam\tCongratulations, a cash prize is yours
+ham\tMum says see you tomorrow night
+spam\tWin free cash every week
+ham\tWant to grab lunch later
+spam\tYour free prize voucher is waiting, claim it
+ham\tTomorrow works, see you then
+spam\tCash bonus for you, claim free now
+ham\tI saved you a seat at lunch
+";
+
+    /// Contents of the raw string literals in generated code, in order.
+    fn payloads(source: &str) -> Vec<&str> {
+        source
+            .split("r#\"")
+            .skip(1)
+            .map(|rest| rest.split_once("\"#").expect("unterminated literal").0)
+            .collect()
+    }
+
+    #[test]
+    fn test_split_seed() {
+        assert_eq!(split_seed(None).unwrap(), DEFAULT_SPLIT_SEED);
+        assert_eq!(split_seed(Some(OsStr::new("42"))).unwrap(), 42);
+
+        let err = split_seed(Some(OsStr::new("ten"))).unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidInput);
+        assert_eq!(
+            err.to_string(),
+            "invalid SMS_SPLIT_SEED `ten`, expected a non-negative integer"
+        );
+    }
+
+    #[test]
+    fn test_check_accuracy() {
+        let dir = std::env::temp_dir().join("sms-data-clean-check-accuracy");
+        std::fs::create_dir_all(&dir).expect("failed to create temp dir");
+        let dataset = dir.join("fixture.tsv");
+        std::fs::write(&dataset, FIXTURE).expect("failed to write fixture");
+
+        let mut trained = train(&dataset).expect("failed to train");
+        assert!(trained.confusion.total() > 0);
+        trained.accuracy = 0.5;
+        let err = check_accuracy(&trained, MIN_ACCURACY).unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+        let message = err.to_string();
+        assert!(message.starts_with("held-out accuracy 0.500 is not above the required 0.900"));
+        assert!(message.contains(&trained.confusion.to_string()));
+        assert!(message.contains("too small"));
+
+        assert!(check_accuracy(&trained, 0.4).is_ok());
+    }
+
+    #[test]
+    fn test_split_seed_changes_training_set() {
+        let dir = std::env::temp_dir().join("sms-data-clean-split-seed");
+        std::fs::create_dir_all(&dir).expect("failed to create temp dir");
+        let dataset = dir.join("fixture.tsv");
+        std::fs::write(&dataset, FIXTURE).expect("failed to write fixture");
+
+        // The fitted counts tell which messages the model was trained on.
+        let fitted = |seed: u64| {
+            let trained = train_with_seed(&dataset, seed).expect("failed to train");
+            serde_json::to_string(&trained.model).unwrap()
+        };
+        assert_eq!(fitted(1), fitted(1));
+        assert_ne!(fitted(1), fitted(2));
+    }
+
+    #[test]
+    fn test_train_drops_empty_records() {
+        let dir = std::env::temp_dir().join("sms-data-clean-train-empty");
+        std::fs::create_dir_all(&dir).expect("failed to create temp dir");
+        let dataset = dir.join("fixture.tsv");
+        std::fs::write(&dataset, FIXTURE).expect("failed to write fixture");
+        let with_empty = dir.join("with-empty.tsv");
+        // Made only of stop words, so left without tokens.
+        let empty = "ham\tYou are there?\nspam\tIt is what it is\nham\tWhere are you?\n";
+        let lines = FIXTURE.lines().collect::<Vec<_>>();
+        let (head, tail) = lines.split_at(lines.len() / 2);
+        let contents = format!(
+            "{empty}{}\n{empty}{}\n{empty}",
+            head.join("\n"),
+            tail.join("\n")
+        );
+        std::fs::write(&with_empty, contents).expect("failed to write fixture");
+
+        let expected = train(&dataset).expect("failed to train");
+        let trained = train(&with_empty).expect("failed to train");
+        assert_eq!(trained.confusion, expected.confusion);
+        assert_eq!(trained.vocabulary, expected.vocabulary);
+        assert_eq!(trained.calibration, expected.calibration);
+    }
+
+    #[test]
+    fn test_saved_model_roundtrip() {
+        let dir = std::env::temp_dir().join("sms-data-clean-saved-model");
+        std::fs::create_dir_all(&dir).expect("failed to create temp dir");
+        let dataset = dir.join("fixture.tsv");
+        std::fs::write(&dataset, FIXTURE).expect("failed to write fixture");
+
+        let trained = train(&dataset).expect("failed to train");
+        let path = dir.join("model.json");
+        trained.save(&path).expect("failed to save");
+        let classifier = load_classifier(&path).expect("failed to load");
+        assert_eq!(classifier.vocabulary(), &trained.vocabulary);
+        assert!(
+            classifier
+                .predict("claim your free cash prize")
+                .unwrap()
+                .spam
+        );
+
+        std::fs::write(&path, "{}").unwrap();
+        assert!(load_classifier(&path).is_err());
+    }
+
+    /// Checks the payloads of the generated source. The `spam-model` crate compiles
+    /// the source generated by its build script and calls `classifier()`.
+    #[test]
+    fn test_generated_module_roundtrip() {
+        let dir = std::env::temp_dir().join("sms-data-clean-codegen");
+        std::fs::create_dir_all(&dir).expect("failed to create temp dir");
+        let dataset = dir.join("fixture.tsv");
+        std::fs::write(&dataset, FIXTURE).expect("failed to write fixture");
+
+        let trained = train(&dataset).expect("failed to train");
+        let module = dir.join("model.rs");
+        generate_model_module(&trained, &module).expect("failed to generate");
+        let source = std::fs::read_to_string(&module).expect("failed to read module");
+        assert_eq!(source, model_module(&trained).expect("failed to generate"));
+
+        assert!(source.contains("pub fn naive_bayes_model() -> MultinomialNB<usize, usize, DenseMatrix<usize>, Vec<usize>>"));
+        assert!(source.contains("pub fn vocabulary() -> sms_data_clean::Vocabulary"));
+        assert!(source.contains("pub fn calibration() -> sms_data_clean::PlattScaling"));
+        assert!(source.contains("pub fn classifier() -> sms_data_clean::Classifier"));
+
+        let payloads = payloads(&source);
+        assert_eq!(payloads.len(), 3);
+        let model: NaiveBayesModel = serde_json::from_str(payloads[0]).expect("invalid model");
+        let vocabulary: Vocabulary = serde_json::from_str(payloads[1]).expect("invalid vocabulary");
+        let calibration: PlattScaling =
+            serde_json::from_str(payloads[2]).expect("invalid calibration");
+        assert_eq!(vocabulary, trained.vocabulary);
+        assert_eq!(calibration, trained.calibration);
+        assert_eq!(model.n_features(), vocabulary.len());
+
+        let classifier = Classifier::new(model, vocabulary, calibration).expect("invalid model");
+        assert!(
+            classifier
+                .predict("claim your free cash prize")
+                .unwrap()
+                .spam
+        );
+        assert!(
+            !classifier
+                .predict("see you at lunch tomorrow")
+                .unwrap()
+                .spam
+        );
+    }
+}
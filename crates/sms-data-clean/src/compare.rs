@@ -0,0 +1,220 @@
+use std::fmt;
+use std::fs::File;
+use std::io::BufReader;
+use std::path::Path;
+
+use crate::{
+    Classifier, ConfusionMatrix, Label, NaiveBayesModel, PlattScaling, RawDataset, TextClassifier,
+    Vocabulary,
+};
+
+/// Head-to-head result of [`compare_models`] on one test set.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ModelComparison {
+    pub a: ConfusionMatrix,
+    pub b: ConfusionMatrix,
+    /// Messages only model `a` got right.
+    pub only_a_correct: usize,
+    /// Messages only model `b` got right.
+    pub only_b_correct: usize,
+    /// Two-sided exact McNemar p-value of the disagreements: how likely a split at
+    /// least this lopsided is if both models are equally accurate.
+    pub p_value: f64,
+}
+
+impl fmt::Display for ModelComparison {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (a, b) = (&self.a, &self.b);
+        writeln!(f, "{:>12}{:>10}{:>10}", "", "a", "b")?;
+        for (name, a, b) in [
+            ("accuracy", a.accuracy(), b.accuracy()),
+            (
+                "precision",
+                a.precision(Label::Spam),
+                b.precision(Label::Spam),
+            ),
+            ("recall", a.recall(Label::Spam), b.recall(Label::Spam)),
+            ("f1", a.f1(Label::Spam), b.f1(Label::Spam)),
+        ] {
+            writeln!(f, "{:>12}{:>10.4}{:>10.4}", name, a, b)?;
+        }
+        writeln!(
+            f,
+            "only a correct: {}, only b correct: {}, McNemar p = {:.4}",
+            self.only_a_correct, self.only_b_correct, self.p_value
+        )
+    }
+}
+
+/// Scores both classifiers on every message of `test` and compares them, see
+/// [`ModelComparison`]. Precision, recall and F1 are those of spam.
+pub fn compare_models<A, B>(
+    a: &A,
+    b: &B,
+    test: &RawDataset,
+) -> Result<ModelComparison, std::io::Error>
+where
+    A: TextClassifier + ?Sized,
+    B: TextClassifier + ?Sized,
+{
+    let predicted_a = predictions(a, test)?;
+    let predicted_b = predictions(b, test)?;
+    let truth = test.data.iter().map(|data| data.label).collect::<Vec<_>>();
+
+    let (mut only_a_correct, mut only_b_correct) = (0, 0);
+    for ((truth, a), b) in truth.iter().zip(&predicted_a).zip(&predicted_b) {
+        match (a == truth, b == truth) {
+            (true, false) => only_a_correct += 1,
+            (false, true) => only_b_correct += 1,
+            _ => {}
+        }
+    }
+    Ok(ModelComparison {
+        a: ConfusionMatrix::new(&truth, &predicted_a),
+        b: ConfusionMatrix::new(&truth, &predicted_b),
+        only_a_correct,
+        only_b_correct,
+        p_value: mcnemar_p_value(only_a_correct, only_b_correct),
+    })
+}
+
+fn predictions<C: TextClassifier + ?Sized>(
+    classifier: &C,
+    test: &RawDataset,
+) -> Result<Vec<Label>, std::io::Error> {
+    test.data
+        .iter()
+        .map(|data| {
+            let prediction = classifier
+                .predict(&data.sms)
+                .map_err(|err| std::io::Error::new(std::io::ErrorKind::Other, err.to_string()))?;
+            Ok(if prediction.spam {
+                Label::Spam
+            } else {
+                Label::Ham
+            })
+        })
+        .collect()
+}
+
+/// Like [`compare_models`] for two models serialized as JSON together with their
+/// [`Vocabulary::save`] files, on a labeled file read with [`RawDataset::from_file`].
+/// Both models are assumed to use the [`Tokenizer::standard`](crate::Tokenizer::standard)
+/// tokenization.
+pub fn compare_model_files<P: AsRef<Path>>(
+    (model_a, vocabulary_a): (P, P),
+    (model_b, vocabulary_b): (P, P),
+    test: P,
+) -> Result<ModelComparison, std::io::Error> {
+    let load = |model: P, vocabulary: P| -> Result<Classifier, std::io::Error> {
+        let model: NaiveBayesModel = serde_json::from_reader(BufReader::new(File::open(model)?))?;
+        Classifier::new(
+            model,
+            Vocabulary::load(vocabulary)?,
+            PlattScaling::default(),
+        )
+    };
+    let a = load(model_a, vocabulary_a)?;
+    let b = load(model_b, vocabulary_b)?;
+    compare_models(&a, &b, &RawDataset::from_file(test)?)
+}
+
+/// Two-sided exact McNemar test: the probability under `Binomial(n, 1/2)`, with
+/// `n = only_a + only_b`, of a split at least as uneven as the observed one.
+pub fn mcnemar_p_value(only_a: usize, only_b: usize) -> f64 {
+    let n = only_a + only_b;
+    let k = only_a.min(only_b);
+    // Sum the binomial tail in log space, 2^-n underflows for a few thousand
+    // disagreements.
+    let mut ln_choose = 0.0;
+    let mut tail = 0.0;
+    for i in 0..=k {
+        if i > 0 {
+            ln_choose += ((n - i + 1) as f64).ln() - (i as f64).ln();
+        }
+        tail += (ln_choose - n as f64 * std::f64::consts::LN_2).exp();
+    }
+    (2.0 * tail).min(1.0)
+}
+
+#[cfg(test)]
+mod test {
+    use crate::fixtures::{raw_dataset, train};
+    use crate::Label;
+
+    use super::{compare_model_files, compare_models, mcnemar_p_value};
+
+    #[test]
+    fn test_mcnemar_p_value() {
+        assert_eq!(mcnemar_p_value(0, 0), 1.0);
+        assert_eq!(mcnemar_p_value(3, 3), 1.0);
+        assert!((mcnemar_p_value(0, 6) - 0.03125).abs() < 1e-12);
+        assert!((mcnemar_p_value(6, 0) - 0.03125).abs() < 1e-12);
+        // 2 * P(X <= 1) for X ~ Binomial(10, 1/2) = 2 * 11 / 1024.
+        assert!((mcnemar_p_value(1, 9) - 22.0 / 1024.0).abs() < 1e-12);
+        assert!(mcnemar_p_value(2000, 3000) < 1e-20);
+    }
+
+    const TRAIN: &[(Label, &str)] = &[
+        (Label::Spam, "win a free prize now"),
+        (Label::Spam, "claim your free prize"),
+        (Label::Ham, "see you at lunch"),
+        (Label::Ham, "call me when you are home"),
+    ];
+
+    const TEST: &[(Label, &str)] = &[
+        (Label::Spam, "free prize inside"),
+        (Label::Spam, "urgent reply to claim"),
+        (Label::Ham, "lunch at home"),
+        (Label::Ham, "call me"),
+    ];
+
+    #[test]
+    fn test_compare_models() {
+        let a = train(TRAIN);
+        let mut extended = TRAIN.to_vec();
+        extended.push((Label::Spam, "urgent reply now"));
+        let b = train(&extended);
+
+        let test = raw_dataset(TEST);
+        let comparison = compare_models(&a, &b, &test).expect("failed to compare");
+        assert_eq!(comparison.a.total(), TEST.len());
+        assert_eq!(comparison.b.total(), TEST.len());
+        assert!((0.0..=1.0).contains(&comparison.p_value));
+        assert!(comparison.to_string().contains("McNemar p = "));
+
+        let same = compare_models(&a, &a, &test).expect("failed to compare");
+        assert_eq!((same.only_a_correct, same.only_b_correct), (0, 0));
+        assert_eq!(same.p_value, 1.0);
+        assert_eq!(same.a, same.b);
+    }
+
+    #[test]
+    fn test_compare_model_files() {
+        let dir = std::env::temp_dir().join("sms-data-clean-compare");
+        std::fs::create_dir_all(&dir).expect("failed to create temp dir");
+        let classifier = train(TRAIN);
+        let (model, vocabulary) = (dir.join("model.json"), dir.join("vocabulary.json"));
+        std::fs::write(&model, serde_json::to_vec(classifier.model()).unwrap())
+            .expect("failed to write model");
+        classifier
+            .vocabulary()
+            .save(&vocabulary)
+            .expect("failed to write vocabulary");
+        let test = dir.join("test.tsv");
+        let lines = TEST
+            .iter()
+            .map(|(label, sms)| format!("{label}\t{sms}\n"))
+            .collect::<String>();
+        std::fs::write(&test, lines).expect("failed to write test file");
+
+        let comparison = compare_model_files(
+            (model.clone(), vocabulary.clone()),
+            (model, vocabulary),
+            test,
+        )
+        .expect("failed to compare");
+        let expected = compare_models(&classifier, &classifier, &raw_dataset(TEST)).unwrap();
+        assert_eq!(comparison, expected);
+    }
+}
@@ -0,0 +1,191 @@
+use std::collections::hash_map::Entry;
+use std::collections::{HashMap, HashSet};
+
+use smartcore::linalg::basic::matrix::DenseMatrix;
+use smartcore::numbers::basenum::Number;
+
+use crate::{
+    ensure_vocabulary, try_bag_of_words, Dataset, Label, LabelEncoder, RawDataset, Vocabulary,
+};
+
+impl RawDataset {
+    /// Collapses records with the same label, subject and message into one, keeping
+    /// the first of them, and returns how many times each record occurred, to train
+    /// on with [`Dataset::to_smartcore_with_weights`] instead of dropping the copies.
+    pub fn collapse_duplicates(self) -> (RawDataset, Vec<usize>) {
+        let mut index: HashMap<(Label, Option<String>, String), usize> = HashMap::new();
+        let mut data = Vec::new();
+        let mut weights = Vec::new();
+        for row in self.data {
+            match index.entry((row.label, row.subject.clone(), row.sms.clone())) {
+                Entry::Occupied(entry) => weights[*entry.get()] += 1,
+                Entry::Vacant(entry) => {
+                    entry.insert(data.len());
+                    data.push(row);
+                    weights.push(1);
+                }
+            }
+        }
+        (RawDataset { data }, weights)
+    }
+}
+
+impl Dataset {
+    /// Like [`Dataset::to_smartcore`], repeating the row of every document `weight`
+    /// times, which trains `MultinomialNB` exactly like the literal copies would,
+    /// class priors included. Only the tokenizing and counting of the copies is saved,
+    /// the matrix holds as many rows as the copies would. Fails unless there is one
+    /// weight per document.
+    pub fn to_smartcore_with_weights<T: Number>(
+        self,
+        weights: &[usize],
+    ) -> Result<(DenseMatrix<T>, Vec<T>, Vocabulary), std::io::Error> {
+        if weights.len() != self.len() {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                format!("{} weights for {} documents", weights.len(), self.len()),
+            ));
+        }
+        let vocabulary = Vocabulary::from_dataset(&self);
+        ensure_vocabulary(&vocabulary)?;
+
+        let encoder = LabelEncoder::default();
+        let mut rows = Vec::with_capacity(weights.iter().sum());
+        let mut labels = Vec::with_capacity(rows.capacity());
+        for ((label, data), weight) in self.labels.into_iter().zip(self.data).zip(weights) {
+            let row = try_bag_of_words::<T>(data.tokens, &vocabulary)?;
+            for _ in 0..*weight {
+                rows.push(row.clone());
+                labels.push(encoder.encode(label));
+            }
+        }
+        Ok((DenseMatrix::from_2d_vec(&rows), labels, vocabulary))
+    }
+
+    /// Groups documents whose sets of `shingle_size`-token shingles have a Jaccard
+    /// similarity of at least `threshold`, e.g. spam campaigns varying a word.
+    ///
+    /// Returns the indices of every cluster with two or more documents, in order of
+    /// their first document. Clusters are transitive: two documents can end up in
+    /// the same cluster through a third one similar to both. Documents shorter than
+    /// `shingle_size` form a single shingle, documents without tokens are skipped.
+    pub fn near_duplicates(&self, shingle_size: usize, threshold: f64) -> Vec<Vec<usize>> {
+        let shingle_size = shingle_size.max(1);
+        let shingles = self
+            .data
+            .iter()
+            .map(|data| {
+                data.tokens
+                    .windows(shingle_size.min(data.tokens.len()).max(1))
+                    .map(|window| window.join(" "))
+                    .collect::<HashSet<_>>()
+            })
+            .collect::<Vec<_>>();
+
+        let mut postings: HashMap<&str, Vec<usize>> = HashMap::new();
+        for (document, shingles) in shingles.iter().enumerate() {
+            for shingle in shingles {
+                postings.entry(shingle).or_default().push(document);
+            }
+        }
+
+        let mut parents = (0..shingles.len()).collect::<Vec<_>>();
+        for (document, set) in shingles.iter().enumerate() {
+            let candidates = set
+                .iter()
+                .flat_map(|shingle| &postings[shingle.as_str()])
+                .filter(|other| **other > document)
+                .collect::<HashSet<_>>();
+            for other in candidates {
+                let shared = set.intersection(&shingles[*other]).count();
+                let union = set.len() + shingles[*other].len() - shared;
+                if shared as f64 / union as f64 >= threshold {
+                    let (a, b) = (root(&mut parents, document), root(&mut parents, *other));
+                    parents[a.max(b)] = a.min(b);
+                }
+            }
+        }
+
+        let mut clusters: Vec<Vec<usize>> = Vec::new();
+        let mut cluster_of_root = HashMap::new();
+        for document in 0..shingles.len() {
+            let leader = root(&mut parents, document);
+            match cluster_of_root.get(&leader) {
+                Some(cluster) => clusters[*cluster].push(document),
+                None => {
+                    cluster_of_root.insert(leader, clusters.len());
+                    clusters.push(vec![document]);
+                }
+            }
+        }
+        clusters.retain(|cluster| cluster.len() > 1);
+        clusters
+    }
+}
+
+/// Union-find root of `node`, compressing the path on the way.
+fn root(parents: &mut [usize], mut node: usize) -> usize {
+    while parents[node] != node {
+        parents[node] = parents[parents[node]];
+        node = parents[node];
+    }
+    node
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{fixtures, matrix_rows, Label};
+
+    #[test]
+    fn test_near_duplicates() {
+        let dataset = fixtures::dataset(&[
+            (
+                Label::Spam,
+                "urgent you have won a guaranteed cash prize of 1000 pounds \
+                 to claim it call our hotline before midnight tonight please",
+            ),
+            (Label::Ham, "see you at lunch tomorrow"),
+            (
+                Label::Spam,
+                "urgent you have won a guaranteed cash award of 1000 pounds \
+                 to claim it call our hotline before midnight tonight please",
+            ),
+            (Label::Ham, "see you at dinner tomorrow"),
+        ]);
+
+        assert_eq!(dataset.near_duplicates(2, 0.8), vec![vec![0, 2]]);
+        assert_eq!(
+            dataset.near_duplicates(1, 0.6),
+            vec![vec![0, 2], vec![1, 3]]
+        );
+    }
+
+    #[test]
+    fn test_collapse_duplicates() {
+        let rows = [
+            (Label::Spam, "win a free prize"),
+            (Label::Spam, "win a free prize"),
+            (Label::Spam, "win a free prize"),
+            (Label::Ham, "win a free prize"),
+            (Label::Ham, "see you at lunch"),
+        ];
+        let raw = || fixtures::raw_dataset(&rows);
+
+        let (collapsed, weights) = raw().collapse_duplicates();
+        assert_eq!(collapsed.len(), 3);
+        assert_eq!(weights, vec![3, 1, 1]);
+        assert_eq!(collapsed.data[1].label, Label::Ham);
+
+        let (x, y, vocabulary) = collapsed
+            .tokenize()
+            .to_smartcore_with_weights::<usize>(&weights)
+            .expect("failed to vectorize");
+        let (expected_x, expected_y, expected_vocabulary) = raw()
+            .tokenize()
+            .to_smartcore::<usize>()
+            .expect("failed to vectorize");
+        assert_eq!(vocabulary, expected_vocabulary);
+        assert_eq!(y, expected_y);
+        assert_eq!(matrix_rows(&x), matrix_rows(&expected_x));
+    }
+}
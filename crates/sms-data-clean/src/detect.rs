@@ -0,0 +1,120 @@
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use std::path::Path;
+
+use crate::{Label, RawData, RawDataset};
+
+/// Number of leading lines inspected by [`RawDataset::from_reader_detect_columns`].
+const SAMPLE_LINES: usize = 20;
+
+/// Layout guessed by [`RawDataset::from_file_detect_columns`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ColumnGuess {
+    /// Tab separated column holding the labels.
+    pub label: usize,
+    /// Tab separated column holding the message.
+    pub text: usize,
+}
+
+/// Label spellings recognised while guessing the layout.
+fn guess_label(value: &str) -> Option<Label> {
+    match value.trim().to_ascii_lowercase().as_str() {
+        "ham" | "0" => Some(Label::Ham),
+        "spam" | "1" => Some(Label::Spam),
+        _ => None,
+    }
+}
+
+impl RawDataset {
+    /// Opt-in loader for tab separated files of unknown layout, see
+    /// [`RawDataset::from_reader_detect_columns`].
+    pub fn from_file_detect_columns<P>(path: P) -> Result<(Self, ColumnGuess), std::io::Error>
+    where
+        P: AsRef<Path>,
+    {
+        Self::from_reader_detect_columns(BufReader::new(File::open(path)?))
+    }
+
+    /// Guesses from the first lines which column holds the labels (only `ham`/`spam`/`0`/`1`
+    /// values) and which the message (the longest remaining one), then loads every
+    /// line with that layout.
+    pub fn from_reader_detect_columns<R: BufRead>(
+        reader: R,
+    ) -> Result<(Self, ColumnGuess), std::io::Error> {
+        let lines = reader.lines().collect::<Result<Vec<_>, std::io::Error>>()?;
+        let sample = lines
+            .iter()
+            .take(SAMPLE_LINES)
+            .map(|line| line.split('\t').collect::<Vec<_>>())
+            .collect::<Vec<_>>();
+        let columns = sample.iter().map(Vec::len).min().unwrap_or(0);
+
+        let label = (0..columns)
+            .find(|column| {
+                sample
+                    .iter()
+                    .all(|fields| guess_label(fields[*column]).is_some())
+            })
+            .ok_or_else(|| {
+                std::io::Error::new(std::io::ErrorKind::InvalidData, "No label column found")
+            })?;
+        let text = (0..columns)
+            .filter(|column| *column != label)
+            .max_by_key(|column| {
+                sample
+                    .iter()
+                    .map(|fields| fields[*column].len())
+                    .sum::<usize>()
+            })
+            .ok_or_else(|| {
+                std::io::Error::new(std::io::ErrorKind::InvalidData, "No text column found")
+            })?;
+        let guess = ColumnGuess { label, text };
+
+        let data = lines
+            .iter()
+            .map(|line| {
+                let fields = line.split('\t').collect::<Vec<_>>();
+                let (label, sms) = match (fields.get(guess.label), fields.get(guess.text)) {
+                    (Some(label), Some(sms)) => (label, sms),
+                    _ => {
+                        return Err(std::io::Error::new(
+                            std::io::ErrorKind::Other,
+                            "Missing delimeter",
+                        ))
+                    }
+                };
+                let label = guess_label(label).ok_or_else(|| {
+                    std::io::Error::new(std::io::ErrorKind::Other, "Invalid label")
+                })?;
+                Ok(RawData::new(label, *sms))
+            })
+            .collect::<Result<Vec<_>, std::io::Error>>()?;
+        Ok((Self { data }, guess))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{Label, RawDataset};
+
+    use super::ColumnGuess;
+
+    #[test]
+    fn test_detect_bundled_corpus() {
+        let (dataset, guess) = RawDataset::from_file_detect_columns("../../SMSSpamCollection")
+            .expect("creation failed");
+        assert_eq!(guess, ColumnGuess { label: 0, text: 1 });
+        assert_eq!(dataset.len(), 5574);
+    }
+
+    #[test]
+    fn test_detect_swapped_columns() {
+        let input = std::io::Cursor::new(b"42\tSee you at six\t0\n43\tWin a prize now\t1\n");
+        let (dataset, guess) =
+            RawDataset::from_reader_detect_columns(input).expect("creation failed");
+        assert_eq!(guess, ColumnGuess { label: 2, text: 1 });
+        assert_eq!(dataset.data[1].label, Label::Spam);
+        assert_eq!(dataset.data[1].sms, "Win a prize now");
+    }
+}
@@ -0,0 +1,87 @@
+use smartcore::numbers::basenum::Number;
+
+use crate::Label;
+
+/// Maps labels to the class indices of the target vector.
+///
+/// The positive class is encoded as `1` and the other one as `0`, by default
+/// [`Label::Spam`] is positive, which is what the metrics and the calibration
+/// treat as the class being detected.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LabelEncoder {
+    positive: Label,
+}
+
+impl Default for LabelEncoder {
+    fn default() -> Self {
+        Self::with_positive(Label::Spam)
+    }
+}
+
+impl LabelEncoder {
+    pub fn with_positive(positive: Label) -> Self {
+        Self { positive }
+    }
+
+    /// The label encoded as `1`.
+    pub fn positive(&self) -> Label {
+        self.positive
+    }
+
+    /// The label encoded as `0`.
+    pub fn negative(&self) -> Label {
+        match self.positive {
+            Label::Ham => Label::Spam,
+            Label::Spam => Label::Ham,
+        }
+    }
+
+    pub fn encode<T: Number>(&self, label: Label) -> T {
+        if label == self.positive {
+            T::one()
+        } else {
+            T::zero()
+        }
+    }
+
+    pub fn encode_all<T: Number>(&self, labels: Vec<Label>) -> Vec<T> {
+        labels.into_iter().map(|label| self.encode(label)).collect()
+    }
+
+    /// Returns `None` for indices other than `0` and `1`.
+    pub fn decode(&self, index: usize) -> Option<Label> {
+        match index {
+            0 => Some(self.negative()),
+            1 => Some(self.positive),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{fixtures, Label, LabelEncoder};
+
+    #[test]
+    fn test_swapped_mapping() {
+        let dataset = || {
+            fixtures::dataset(&[
+                (Label::Spam, "free prize"),
+                (Label::Ham, "lunch later"),
+                (Label::Ham, "see you"),
+            ])
+        };
+        let (_, default, _) = dataset()
+            .to_smartcore::<usize>()
+            .expect("failed to convert");
+        let encoder = LabelEncoder::with_positive(Label::Ham);
+        let (_, swapped, _) = dataset()
+            .to_smartcore_with_label_encoder::<usize>(&encoder)
+            .expect("failed to convert");
+
+        assert_eq!(default, vec![1, 0, 0]);
+        assert_eq!(swapped, vec![0, 1, 1]);
+        assert_eq!(encoder.decode(1), Some(Label::Ham));
+        assert_eq!(encoder.decode(2), None);
+    }
+}
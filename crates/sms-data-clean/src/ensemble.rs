@@ -0,0 +1,159 @@
+use smartcore::error::Failed;
+
+use crate::{Decision, Prediction, TextClassifier};
+
+/// How the members of an [`Ensemble`] are combined.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Voting {
+    /// Spam when the mean spam probability is above one half.
+    Soft,
+    /// Spam when more than half of the members say so.
+    Hard,
+}
+
+/// Combines several classifiers into one.
+///
+/// The spam probability is the mean over the members for both voting schemes, ties
+/// go to ham.
+pub struct Ensemble {
+    members: Vec<Box<dyn TextClassifier>>,
+    voting: Voting,
+}
+
+impl Ensemble {
+    pub fn new(voting: Voting) -> Self {
+        Self {
+            members: Vec::new(),
+            voting,
+        }
+    }
+
+    pub fn with_member<C: TextClassifier + 'static>(mut self, member: C) -> Self {
+        self.members.push(Box::new(member));
+        self
+    }
+
+    pub fn len(&self) -> usize {
+        self.members.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.members.is_empty()
+    }
+}
+
+impl TextClassifier for Ensemble {
+    /// Fails when the ensemble has no members or any member fails.
+    fn predict(&self, text: &str) -> Result<Prediction, Failed> {
+        if self.members.is_empty() {
+            return Err(Failed::predict("ensemble has no members"));
+        }
+        let predictions = self
+            .members
+            .iter()
+            .map(|member| member.predict(text))
+            .collect::<Result<Vec<_>, Failed>>()?;
+
+        let n = predictions.len() as f64;
+        let spam_probability = predictions
+            .iter()
+            .map(|prediction| prediction.spam_probability)
+            .sum::<f64>()
+            / n;
+        let spam = match self.voting {
+            Voting::Soft => spam_probability > 0.5,
+            Voting::Hard => {
+                let votes = predictions
+                    .iter()
+                    .filter(|prediction| prediction.spam)
+                    .count();
+                votes as f64 > n / 2.0
+            }
+        };
+        Ok(Prediction {
+            spam,
+            spam_probability,
+            decided_by: Decision::Model,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        retrain_with_extended_vocabulary, Classifier, ConfusionMatrix, Dataset, Label,
+        PlattScaling, RawData, RawDataset, TextClassifier, Vocabulary,
+    };
+
+    use super::{Ensemble, Voting};
+
+    fn member(rows: Vec<RawData>) -> Classifier {
+        let dataset: Dataset = RawDataset { data: rows }
+            .lowercase()
+            .without_punctuaction()
+            .tokenize()
+            .stop_words();
+        let (model, vocabulary) =
+            retrain_with_extended_vocabulary(Vocabulary::new(), dataset).expect("failed to train");
+        Classifier::new(model, vocabulary, PlattScaling::default()).expect("invalid model")
+    }
+
+    fn f1(classifier: &dyn TextClassifier, test: &[RawData]) -> f64 {
+        let truth = test.iter().map(|data| data.label).collect::<Vec<_>>();
+        let predicted = test
+            .iter()
+            .map(|data| {
+                let prediction = classifier
+                    .predict(&data.sms.to_lowercase())
+                    .expect("failed to predict");
+                if prediction.spam {
+                    Label::Spam
+                } else {
+                    Label::Ham
+                }
+            })
+            .collect::<Vec<_>>();
+        ConfusionMatrix::new(&truth, &predicted).f1(Label::Spam)
+    }
+
+    /// Slack on the F1 of the best member, so that a message flipping either way
+    /// on a change of the preprocessing does not fail the test.
+    const F1_TOLERANCE: f64 = 0.01;
+
+    #[test]
+    fn test_ensemble_not_worse_than_best_member() {
+        // Folds and test set are picked by position, so every run scores the same split.
+        let dataset = RawDataset::from_file("../../SMSSpamCollection").expect("failed to load");
+        let mut folds: [Vec<RawData>; 3] = Default::default();
+        let mut test = Vec::new();
+        for (i, data) in dataset.data.into_iter().enumerate() {
+            if i % 10 < 3 {
+                test.push(data);
+            } else {
+                folds[i % 3].push(data);
+            }
+        }
+
+        let mut ensemble = Ensemble::new(Voting::Soft);
+        let (mut best, mut worst) = (0.0f64, 1.0f64);
+        for fold in folds {
+            let member = member(fold);
+            let score = f1(&member, &test);
+            (best, worst) = (best.max(score), worst.min(score));
+            ensemble = ensemble.with_member(member);
+        }
+        assert_eq!(ensemble.len(), 3);
+
+        let score = f1(&ensemble, &test);
+        assert!(score >= worst, "ensemble f1 {score} < worst member {worst}");
+        assert!(
+            score >= best - F1_TOLERANCE,
+            "ensemble f1 {score} < best member {best} - {F1_TOLERANCE}"
+        );
+    }
+
+    #[test]
+    fn test_empty_ensemble_fails() {
+        assert!(Ensemble::new(Voting::Hard).predict("free prize").is_err());
+    }
+}
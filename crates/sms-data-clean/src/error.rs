@@ -0,0 +1,38 @@
+use std::fmt;
+
+/// Failures specific to this crate.
+///
+/// They are returned wrapped in a [`std::io::Error`] of kind
+/// [`InvalidData`](std::io::ErrorKind::InvalidData), like every other error of the
+/// crate, and can be told apart with [`DataCleanError::from_io`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DataCleanError {
+    /// No token is left to build features from, e.g. after removing the stop words
+    /// of a dataset made of stop words only.
+    EmptyVocabulary,
+}
+
+impl DataCleanError {
+    /// The error wrapped in `err`, if any.
+    pub fn from_io(err: &std::io::Error) -> Option<&Self> {
+        err.get_ref()?.downcast_ref()
+    }
+}
+
+impl fmt::Display for DataCleanError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DataCleanError::EmptyVocabulary => {
+                write!(f, "vocabulary is empty, every token was filtered out")
+            }
+        }
+    }
+}
+
+impl std::error::Error for DataCleanError {}
+
+impl From<DataCleanError> for std::io::Error {
+    fn from(err: DataCleanError) -> Self {
+        std::io::Error::new(std::io::ErrorKind::InvalidData, err)
+    }
+}
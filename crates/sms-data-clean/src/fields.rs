@@ -0,0 +1,232 @@
+use serde_json::Value;
+
+use crate::{Classifier, Dataset, Label, Prediction, Tokenizer};
+
+/// Named text fields of a structured record, e.g. `{"subject", "body",
+/// "sender_name"}`, each counted `weight` times in the feature vector.
+///
+/// This generalizes [`Dataset::to_smartcore_with_subject_weight`] to any number of
+/// fields: a token of a field with weight 3 counts as three occurrences, so the
+/// model leans on that field three times as much as on one with weight 1.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct FieldWeights {
+    fields: Vec<(String, usize)>,
+}
+
+impl FieldWeights {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_field(mut self, name: impl Into<String>, weight: usize) -> Self {
+        self.fields.push((name.into(), weight));
+        self
+    }
+
+    /// Parses comma separated `name:weight` pairs, e.g. `subject:3,body,sender_name:1`,
+    /// where a field without weight counts once.
+    pub fn parse(spec: &str) -> Result<Self, std::io::Error> {
+        let mut fields = Self::new();
+        for field in spec.split(',').map(str::trim).filter(|f| !f.is_empty()) {
+            let (name, weight) = match field.split_once(':') {
+                Some((name, weight)) => {
+                    let weight = weight.trim().parse().map_err(|_| {
+                        std::io::Error::new(
+                            std::io::ErrorKind::InvalidInput,
+                            format!("invalid weight `{weight}` of field `{name}`"),
+                        )
+                    })?;
+                    (name.trim(), weight)
+                }
+                None => (field, 1),
+            };
+            fields = fields.with_field(name, weight);
+        }
+        if fields.is_empty() {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                "no fields given",
+            ));
+        }
+        Ok(fields)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.fields.is_empty()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&str, usize)> {
+        self.fields
+            .iter()
+            .map(|(name, weight)| (name.as_str(), *weight))
+    }
+
+    /// The string fields of `record`, in configuration order. Missing fields and
+    /// fields that are not strings are skipped.
+    pub fn texts<'a>(&'a self, record: &'a Value) -> impl Iterator<Item = (&'a str, usize)> {
+        self.iter()
+            .filter_map(|(name, weight)| Some((record.get(name)?.as_str()?, weight)))
+    }
+
+    /// The string fields of `record` joined with newlines, the text keyword rules
+    /// are checked on.
+    pub fn text(&self, record: &Value) -> String {
+        self.texts(record)
+            .map(|(text, _)| text)
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    /// Tokens of every field of `record`, each with the weight of its field. Fields
+    /// of weight zero are left out.
+    pub fn tokenize(&self, tokenizer: &Tokenizer, record: &Value) -> Vec<(String, usize)> {
+        let mut tokens = Vec::new();
+        for (text, weight) in self.texts(record).filter(|(_, weight)| *weight > 0) {
+            tokens.extend(
+                tokenizer
+                    .tokenize(text)
+                    .into_iter()
+                    .map(|token| (token, weight)),
+            );
+        }
+        tokens
+    }
+
+    /// Tokenizes labeled records for training, see [`FieldWeights::tokenize`]. A
+    /// [`Dataset`] holds plain token lists, so the tokens of a field are repeated as
+    /// often as its weight.
+    pub fn dataset<'a, I>(&self, tokenizer: &Tokenizer, records: I) -> Dataset
+    where
+        I: IntoIterator<Item = (Label, &'a Value)>,
+    {
+        let (labels, token_lists): (Vec<_>, Vec<_>) = records
+            .into_iter()
+            .map(|(label, record)| {
+                let tokens = self
+                    .tokenize(tokenizer, record)
+                    .into_iter()
+                    .flat_map(|(token, weight)| std::iter::repeat(token).take(weight))
+                    .collect::<Vec<_>>();
+                (label, tokens)
+            })
+            .unzip();
+        Dataset::from_tokenized(labels, token_lists).expect("one token list per label")
+    }
+}
+
+impl Classifier {
+    /// Like [`Classifier::predict`] for a structured record, weighting its fields with
+    /// `fields`. The model must have been trained on records weighted the same way,
+    /// e.g. with [`FieldWeights::dataset`]. Keyword rules are checked once on all the
+    /// fields, see [`FieldWeights::text`], so a blocklist hit in any field wins.
+    pub fn predict_fields(&self, record: &Value, fields: &FieldWeights) -> Prediction {
+        if let Some(prediction) = self.rules().decide(&fields.text(record)) {
+            return prediction;
+        }
+        self.predict_features(&self.field_features(record, fields))
+    }
+
+    pub fn field_features(&self, record: &Value, fields: &FieldWeights) -> Vec<usize> {
+        self.weighted_token_features(&fields.tokenize(self.tokenizer(), record))
+    }
+
+    /// Like [`Classifier::token_features`], every occurrence of a token counting its
+    /// weight, e.g. for the tokens of [`FieldWeights::tokenize`].
+    pub fn weighted_token_features<S: AsRef<str>>(&self, tokens: &[(S, usize)]) -> Vec<usize> {
+        let mut counts = vec![0; self.vocabulary().len()];
+        for (token, weight) in tokens {
+            if let Some(index) = self.vocabulary().get(token.as_ref()) {
+                counts[index] += weight;
+            }
+        }
+        counts
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use serde_json::json;
+
+    use crate::{fixtures, matrix_rows, KeywordRules, Label, Tokenizer};
+
+    use super::FieldWeights;
+
+    #[test]
+    fn test_parse() {
+        let fields = FieldWeights::parse("subject:3, body,sender_name:0").unwrap();
+        assert_eq!(
+            fields.iter().collect::<Vec<_>>(),
+            vec![("subject", 3), ("body", 1), ("sender_name", 0)]
+        );
+        assert!(FieldWeights::parse("subject:heavy").is_err());
+        assert!(FieldWeights::parse(" , ").is_err());
+    }
+
+    #[test]
+    fn test_field_weights() {
+        let fields = FieldWeights::new()
+            .with_field("subject", 3)
+            .with_field("body", 1)
+            .with_field("sender_name", 1);
+        let record = json!({
+            "subject": "free prize",
+            "body": "free lunch",
+            "sender_name": 42,
+            "id": "prize",
+        });
+        let records = [(Label::Spam, &record)];
+        let dataset = fields.dataset(&Tokenizer::new(), records);
+
+        let (x, _, vocabulary) = dataset
+            .to_smartcore::<usize>()
+            .expect("failed to vectorize");
+        let counts = matrix_rows(&x).remove(0);
+        let count = |token: &str| counts[vocabulary.get(token).unwrap()];
+        assert_eq!(count("prize"), 3);
+        assert_eq!(count("lunch"), 1);
+        assert_eq!(count("free"), 4);
+        assert_eq!(vocabulary.len(), 3);
+    }
+
+    #[test]
+    fn test_predict_fields() {
+        let classifier = fixtures::classifier();
+        let record = json!({"subject": "free prize", "body": "see you at lunch"});
+
+        let subject_heavy = FieldWeights::new()
+            .with_field("subject", 5)
+            .with_field("body", 1);
+        let body_heavy = FieldWeights::new()
+            .with_field("subject", 1)
+            .with_field("body", 5);
+
+        let heavy = classifier.field_features(&record, &subject_heavy);
+        let prize = classifier.vocabulary().get("prize").unwrap();
+        assert_eq!(heavy[prize], 5);
+        assert!(classifier.predict_fields(&record, &subject_heavy).spam);
+        assert!(!classifier.predict_fields(&record, &body_heavy).spam);
+
+        // Counted, not repeated: a huge weight costs nothing.
+        let huge = FieldWeights::new().with_field("subject", 1_000_000_000);
+        assert_eq!(
+            classifier.field_features(&record, &huge)[prize],
+            1_000_000_000
+        );
+    }
+
+    #[test]
+    fn test_predict_fields_rules() {
+        let classifier = fixtures::classifier().with_rules(KeywordRules {
+            blocklist: vec!["bad.example.com".to_string()],
+            allowlist: vec!["your library account".to_string()],
+        });
+        let fields = FieldWeights::new()
+            .with_field("subject", 1)
+            .with_field("body", 1);
+        let record = json!({
+            "subject": "your library account",
+            "body": "see you at lunch http://bad.example.com",
+        });
+        assert!(classifier.predict_fields(&record, &fields).spam);
+    }
+}
@@ -0,0 +1,44 @@
+//! Small hand-built datasets and models shared by the unit tests.
+use crate::{
+    retrain_with_extended_vocabulary, Classifier, Dataset, Label, PlattScaling, RawData,
+    RawDataset, Tokenizer, Vocabulary,
+};
+
+/// Messages the fixture [`classifier`] is trained on, where "free", "prize",
+/// "win" and "claim" only ever appear in spam.
+pub const TRAINING_ROWS: &[(Label, &str)] = &[
+    (Label::Spam, "win a free prize now"),
+    (Label::Spam, "claim your free prize"),
+    (Label::Spam, "free entry win cash"),
+    (Label::Ham, "see you at lunch"),
+    (Label::Ham, "call me when you are home"),
+    (Label::Ham, "are we still on for lunch"),
+    (Label::Ham, "i will call you later"),
+];
+
+pub fn raw_dataset(rows: &[(Label, &str)]) -> RawDataset {
+    RawDataset {
+        data: rows
+            .iter()
+            .map(|(label, sms)| RawData::new(*label, *sms))
+            .collect(),
+    }
+}
+
+pub fn dataset(rows: &[(Label, &str)]) -> Dataset {
+    raw_dataset(rows).tokenize()
+}
+
+/// Classifier trained on `rows` with the standard tokenizer and an
+/// uncalibrated [`PlattScaling`].
+pub fn train(rows: &[(Label, &str)]) -> Classifier {
+    let dataset = raw_dataset(rows).tokenize_with(&Tokenizer::standard());
+    let (model, vocabulary) = retrain_with_extended_vocabulary(Vocabulary::new(), dataset)
+        .expect("failed to train fixture");
+    Classifier::new(model, vocabulary, PlattScaling::default()).expect("invalid fixture")
+}
+
+/// Classifier trained on [`TRAINING_ROWS`].
+pub fn classifier() -> Classifier {
+    train(TRAINING_ROWS)
+}
@@ -0,0 +1,167 @@
+use std::hash::{BuildHasher, Hasher};
+
+use smartcore::linalg::basic::matrix::DenseMatrix;
+use smartcore::numbers::basenum::Number;
+
+use crate::{Dataset, LabelEncoder};
+
+/// Seeded 64-bit FNV-1a, the default hasher of [`HashingVectorizer`].
+///
+/// Unlike the randomized `RandomState` of `std::collections::HashMap`, which must not
+/// be used for feature hashing, the same seed gives the same hashes across runs,
+/// machines and Rust versions.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct FnvBuildHasher {
+    seed: u64,
+}
+
+impl FnvBuildHasher {
+    pub fn with_seed(seed: u64) -> Self {
+        Self { seed }
+    }
+}
+
+impl BuildHasher for FnvBuildHasher {
+    type Hasher = FnvHasher;
+
+    fn build_hasher(&self) -> FnvHasher {
+        FnvHasher(FnvHasher::OFFSET_BASIS ^ self.seed)
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct FnvHasher(u64);
+
+impl FnvHasher {
+    const OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+    const PRIME: u64 = 0x0000_0100_0000_01b3;
+}
+
+impl Hasher for FnvHasher {
+    fn write(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.0 ^= u64::from(*byte);
+            self.0 = self.0.wrapping_mul(Self::PRIME);
+        }
+    }
+
+    fn finish(&self) -> u64 {
+        self.0
+    }
+}
+
+/// Maps tokens to a fixed number of feature columns by hashing, so no vocabulary
+/// has to be built or shipped. Distinct tokens may share a column.
+///
+/// Feature indices are only as stable as the hasher: keep the default
+/// [`FnvBuildHasher`] or another deterministic one, a model trained on hashed
+/// features is meaningless with a different hasher or seed.
+#[derive(Debug, Clone)]
+pub struct HashingVectorizer<S = FnvBuildHasher> {
+    n_features: usize,
+    build_hasher: S,
+}
+
+impl HashingVectorizer {
+    pub fn new(n_features: usize) -> Self {
+        Self::with_hasher(n_features, FnvBuildHasher::default())
+    }
+}
+
+impl<S: BuildHasher> HashingVectorizer<S> {
+    pub fn with_hasher(n_features: usize, build_hasher: S) -> Self {
+        Self {
+            n_features: n_features.max(1),
+            build_hasher,
+        }
+    }
+
+    pub fn n_features(&self) -> usize {
+        self.n_features
+    }
+
+    /// Column of `token`, hashing its UTF-8 bytes.
+    pub fn index(&self, token: &str) -> usize {
+        let mut hasher = self.build_hasher.build_hasher();
+        hasher.write(token.as_bytes());
+        (hasher.finish() % self.n_features as u64) as usize
+    }
+
+    /// Counts of `tokens` per column, failing when a count does not fit in `T`.
+    pub fn transform<T, I>(&self, tokens: I) -> Result<Vec<T>, std::io::Error>
+    where
+        T: Number,
+        I: IntoIterator,
+        I::Item: AsRef<str>,
+    {
+        let mut counts = vec![0usize; self.n_features];
+        for token in tokens {
+            counts[self.index(token.as_ref())] += 1;
+        }
+        counts
+            .into_iter()
+            .map(|count| {
+                T::from_usize(count).ok_or_else(|| {
+                    std::io::Error::new(
+                        std::io::ErrorKind::InvalidData,
+                        format!("count {count} exceeds the range of the feature type"),
+                    )
+                })
+            })
+            .collect()
+    }
+}
+
+impl Dataset {
+    /// Like [`Dataset::to_smartcore`], with hashed feature columns instead of a vocabulary.
+    pub fn to_hashed<T: Number, S: BuildHasher>(
+        self,
+        vectorizer: &HashingVectorizer<S>,
+    ) -> Result<(DenseMatrix<T>, Vec<T>), std::io::Error> {
+        let labels = LabelEncoder::default().encode_all(self.labels);
+        let data = self
+            .data
+            .iter()
+            .map(|data| vectorizer.transform(&data.tokens))
+            .collect::<Result<Vec<_>, std::io::Error>>()?;
+        Ok((DenseMatrix::from_2d_vec(&data), labels))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use smartcore::linalg::basic::arrays::Array;
+
+    use crate::{fixtures, Label};
+
+    use super::{FnvBuildHasher, HashingVectorizer};
+
+    #[test]
+    fn test_indices_are_stable() {
+        // Expected values are fixed, so any change of these indices between runs,
+        // machines or Rust versions fails here.
+        let vectorizer = HashingVectorizer::new(1024);
+        let indices = ["free", "prize", "lunch"].map(|token| vectorizer.index(token));
+        assert_eq!(indices, [283, 47, 351]);
+
+        let seeded = |seed| HashingVectorizer::with_hasher(1024, FnvBuildHasher::with_seed(seed));
+        assert_eq!(seeded(7).index("free"), 504);
+        assert_eq!(seeded(7).index("free"), seeded(7).index("free"));
+        assert_ne!(seeded(7).index("free"), seeded(0).index("free"));
+    }
+
+    #[test]
+    fn test_to_hashed() {
+        let dataset = fixtures::dataset(&[(Label::Spam, "free free prize"), (Label::Ham, "lunch")]);
+        let vectorizer = HashingVectorizer::new(1024);
+        let (x, y) = dataset
+            .to_hashed::<usize, _>(&vectorizer)
+            .expect("failed to hash");
+
+        assert_eq!(x.shape(), (2, 1024));
+        assert_eq!(*x.get((0, 283)), 2);
+        assert_eq!(*x.get((0, 47)), 1);
+        assert_eq!(*x.get((1, 351)), 1);
+        assert_eq!(y, vec![1, 0]);
+    }
+}
@@ -0,0 +1,37 @@
+/// ISO 639-3 code of the language `text` is most likely written in, e.g. `"eng"`,
+/// or `None` when it cannot be told, as for empty or numeric-only messages.
+///
+/// The stopword list and the bundled model are English only, so other languages
+/// are better tagged or skipped than scored.
+///
+/// Always `None` unless the `language-detection` feature is enabled.
+#[cfg(feature = "language-detection")]
+pub fn detect_language(text: &str) -> Option<&'static str> {
+    whatlang::detect(text).map(|info| info.lang().code())
+}
+
+#[cfg(not(feature = "language-detection"))]
+pub fn detect_language(_text: &str) -> Option<&'static str> {
+    None
+}
+
+#[cfg(test)]
+mod test {
+    use super::detect_language;
+
+    #[test]
+    #[cfg_attr(
+        not(feature = "language-detection"),
+        ignore = "needs the language-detection feature"
+    )]
+    fn test_detect_french() {
+        let language =
+            detect_language("Bonjour, je serai en retard ce soir, ne m'attends pas pour le dîner");
+        assert_eq!(language, Some("fra"));
+        assert_ne!(language, Some("eng"));
+        assert_eq!(
+            detect_language("I will be late tonight, don't wait for me for dinner"),
+            Some("eng")
+        );
+    }
+}
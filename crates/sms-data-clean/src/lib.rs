@@ -1,13 +1,84 @@
-use std::collections::hash_map::Entry;
-use std::collections::HashMap;
-use std::{collections::HashSet, io::BufRead, path::Path, str::FromStr};
+use std::borrow::Cow;
+use std::collections::{BTreeSet, HashMap};
+use std::io::BufRead;
+use std::{path::Path, str::FromStr};
 
-use smartcore::linalg::basic::arrays::{Array1, MutArray};
 use smartcore::linalg::basic::matrix::DenseMatrix;
 use smartcore::numbers::basenum::Number;
-use stopwords::{Language, Stopwords, NLTK};
+use unicode_normalization::UnicodeNormalization;
 
-#[derive(Debug)]
+mod batch;
+mod calibration;
+mod classifier;
+pub mod codegen;
+mod compare;
+mod dedup;
+mod detect;
+mod encoder;
+mod ensemble;
+mod error;
+mod fields;
+#[cfg(test)]
+mod fixtures;
+mod hashing;
+mod language;
+mod metrics;
+#[cfg(feature = "mmap")]
+mod mmap;
+mod model;
+mod multilabel;
+mod pipeline;
+mod progress;
+mod report;
+mod rules;
+mod split;
+mod statistics;
+mod streaming;
+mod tokenizer;
+mod tuning;
+mod validate;
+mod vocabulary;
+
+pub use batch::{score_file, score_reader, CsvWriter, OutputFormat, ScoredText, SCORED_CSV_HEADER};
+pub use calibration::{
+    brier_score, expected_calibration_error, CalibrationReport, PlattScaling, ReliabilityBin,
+};
+pub use classifier::{Classifier, Decision, Prediction, TextClassifier};
+pub use compare::{compare_model_files, compare_models, mcnemar_p_value, ModelComparison};
+pub use detect::ColumnGuess;
+pub use encoder::LabelEncoder;
+pub use ensemble::{Ensemble, Voting};
+pub use error::DataCleanError;
+pub use fields::FieldWeights;
+pub use hashing::{FnvBuildHasher, FnvHasher, HashingVectorizer};
+pub use language::detect_language;
+pub use metrics::{roc_curve, ConfusionMatrix, RocCurve, RocPoint};
+pub use model::{
+    class_log_likelihoods, feature_stats, matrix_rows, matrix_sparsity,
+    retrain_with_extended_vocabulary, spam_log_odds, top_ham_tokens, top_spam_tokens, FeatureStats,
+    LogOddsWeights, NaiveBayesModel, PortableModel, DEFAULT_TOP_TOKENS,
+};
+pub use multilabel::{LabelPolicy, MultiLabelData, MultiLabelDataset};
+pub use pipeline::{
+    Casefold, Lowercase, MinTokenLen, NormalizeNumbers, NormalizeRepeats, NormalizeUnicode,
+    Pipeline, PreprocessStage, SeparatePunctuation, SkipBigrams, SplitDigits, StopWords,
+    TokenStage, TruncateChars, TruncateTokens, WithoutPunctuation,
+};
+use progress::Progress;
+pub use progress::{create_smartcore_input_with_progress, Phase, ProcessedCount};
+pub use report::{
+    confident_mistakes, ClassReport, EvaluationReport, Mistake, REPORT_SCHEMA_VERSION,
+};
+pub use rules::KeywordRules;
+pub use split::{create_train_test_input, stratified_split, HamSample, TrainTestInput};
+pub use statistics::{LengthBucket, StageName};
+pub use streaming::create_smartcore_input_streaming;
+pub use tokenizer::Tokenizer;
+pub use tuning::{search_df_thresholds, DfGrid, DfScore, DfSearch};
+pub use validate::DatasetIssue;
+pub use vocabulary::{vocabulary_jaccard, DocumentFrequencies, VocabConfig, Vocabulary};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub enum Label {
     Ham,
     Spam,
@@ -17,11 +88,80 @@ impl FromStr for Label {
     type Err = ();
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        match s {
-            "ham" => Ok(Label::Ham),
-            "spam" => Ok(Label::Spam),
-            _ => Err(()),
-        }
+        DEFAULT_LABELS
+            .iter()
+            .find(|(name, _)| *name == s)
+            .map(|(_, label)| *label)
+            .ok_or(())
+    }
+}
+
+/// Label strings of the SMS Spam Collection, the ones [`Label::from_str`] accepts.
+pub const DEFAULT_LABELS: &[(&str, Label)] = &[("ham", Label::Ham), ("spam", Label::Spam)];
+
+impl std::fmt::Display for Label {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let label = match self {
+            Label::Ham => "ham",
+            Label::Spam => "spam",
+        };
+        f.pad(label)
+    }
+}
+
+/// Token standing for a run of exclamation marks, see [`RawData::separate_punctuation`].
+pub const MULTI_EXCLAIM_TOKEN: &str = "__MULTIEXCLAIM__";
+
+/// Token standing for a currency amount, see [`RawData::normalize_numbers`].
+pub const MONEY_TOKEN: &str = "__MONEY__";
+
+/// Token standing for any other number, see [`RawData::normalize_numbers`].
+pub const NUMBER_TOKEN: &str = "__NUM__";
+
+/// Reserved feature of [`Dataset::to_smartcore_with_bias`], one in every row.
+pub const BIAS_TOKEN: &str = "__BIAS__";
+
+/// Reserved feature of [`Dataset::to_smartcore_with_all_caps`].
+pub const ALL_CAPS_TOKEN: &str = "__ALLCAPS__";
+
+/// Currency symbols that turn an adjacent number into a [`MONEY_TOKEN`].
+const CURRENCY_SYMBOLS: [char; 3] = ['£', '$', '€'];
+
+/// Unicode normalization form, see [`RawData::normalize_unicode`].
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum UnicodeForm {
+    /// Canonical composition: decomposed sequences like "e" and a combining accent
+    /// become the precomposed "é".
+    #[default]
+    Nfc,
+    /// Compatibility composition: also maps look-alikes to their plain form, e.g.
+    /// full-width "ＦＲＥＥ" to "FREE" and the "ﬁ" ligature to "fi".
+    Nfkc,
+}
+
+/// Separators of formatted numbers, which differ between locales.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct NumberFormat {
+    pub decimal: char,
+    pub thousands: char,
+}
+
+impl NumberFormat {
+    /// `1,000.00`
+    pub const US: Self = Self {
+        decimal: '.',
+        thousands: ',',
+    };
+    /// `1.000,00`, as in most of continental Europe.
+    pub const EUROPEAN: Self = Self {
+        decimal: ',',
+        thousands: '.',
+    };
+}
+
+impl Default for NumberFormat {
+    fn default() -> Self {
+        Self::US
     }
 }
 
@@ -29,61 +169,533 @@ impl FromStr for Label {
 pub struct RawData {
     pub label: Label,
     pub sms: String,
+    /// Subject line for email-style records, tokenized together with the body.
+    pub subject: Option<String>,
 }
 
 #[derive(Debug, Default, Clone)]
 pub struct TokenizedData {
     pub tokens: Vec<String>,
+    /// Number of leading `tokens` that come from the subject.
+    pub subject_len: usize,
+    /// Message the tokens were produced from, kept for error analysis when
+    /// tokenizing with [`Tokenizer::with_keep_original`].
+    pub original: Option<String>,
+}
+
+impl TokenizedData {
+    /// Tokens without a subject.
+    pub fn new(tokens: Vec<String>) -> Self {
+        Self {
+            tokens,
+            subject_len: 0,
+            original: None,
+        }
+    }
+
+    pub fn subject_tokens(&self) -> &[String] {
+        &self.tokens[..self.subject_len]
+    }
+
+    pub fn body_tokens(&self) -> &[String] {
+        &self.tokens[self.subject_len..]
+    }
+
+    /// Keeps the tokens matching `keep`, both in the subject and in the body.
+    pub(crate) fn retain<F: FnMut(&str) -> bool>(self, mut keep: F) -> Self {
+        self.filter_map(|token| keep(&token).then_some(token))
+    }
+
+    /// Replaces every token with the result of `f`, dropping it on `None`.
+    pub(crate) fn filter_map<F: FnMut(String) -> Option<String>>(self, mut f: F) -> Self {
+        let original_subject_len = self.subject_len;
+        let mut subject_len = 0;
+        let tokens = self
+            .tokens
+            .into_iter()
+            .enumerate()
+            .filter_map(|(position, token)| {
+                let token = f(token)?;
+                if position < original_subject_len {
+                    subject_len += 1;
+                }
+                Some(token)
+            })
+            .collect();
+        Self {
+            tokens,
+            subject_len,
+            original: self.original,
+        }
+    }
 }
 
 impl RawData {
-    pub fn lowercase(self) -> Self {
+    pub fn new(label: Label, sms: impl Into<String>) -> Self {
         Self {
-            label: self.label,
-            sms: self.sms.to_lowercase(),
+            label,
+            sms: sms.into(),
+            subject: None,
         }
     }
 
+    /// Parses an RFC-822-ish email: headers, a blank line, then the body.
+    ///
+    /// Only the `Subject` header is kept. The text before the first blank line is
+    /// only taken as headers when every line of it is a `Name: value` header or a
+    /// folded continuation, otherwise the whole text is the body. When there is no
+    /// blank line, a leading `Subject:` line is still recognised (as in the Enron
+    /// corpus) and the rest of the text is taken as the body.
+    pub fn from_email(label: Label, email: &str) -> Self {
+        let email = email.replace("\r\n", "\n");
+        let (headers, body) = match email.split_once("\n\n") {
+            Some((headers, body)) if is_header_block(headers) => (headers, body),
+            Some(_) => ("", email.as_str()),
+            None => match email.split_once('\n') {
+                Some((first, body)) if subject_header(first).is_some() => (first, body),
+                _ => ("", email.as_str()),
+            },
+        };
+        Self {
+            label,
+            sms: body.trim().to_string(),
+            subject: headers.lines().find_map(subject_header),
+        }
+    }
+
+    pub fn lowercase(self) -> Self {
+        self.map_text(|text| text.to_lowercase())
+    }
+
+    /// Like [`RawData::lowercase`], but with Unicode case folding, see [`casefold`].
+    pub fn casefold(self, turkic: bool) -> Self {
+        self.map_text(|text| casefold(text, turkic))
+    }
+
     pub fn without_punctuaction(self) -> Self {
+        self.map_text(|text| text.chars().filter(|c| !c.is_ascii_punctuation()).collect())
+    }
+
+    /// Keeps punctuation as tokens of their own instead of deleting it.
+    ///
+    /// With `multi_exclaim`, runs of two or more `!` become a single
+    /// [`MULTI_EXCLAIM_TOKEN`]. Use instead of [`RawData::without_punctuaction`],
+    /// which would delete the separated tokens again.
+    pub fn separate_punctuation(self, multi_exclaim: bool) -> Self {
+        self.map_text(|text| {
+            let mut separated = String::with_capacity(text.len());
+            let mut chars = text.chars().peekable();
+            while let Some(c) = chars.next() {
+                if c == '!' && multi_exclaim && chars.peek() == Some(&'!') {
+                    while chars.peek() == Some(&'!') {
+                        chars.next();
+                    }
+                    separated.push(' ');
+                    separated.push_str(MULTI_EXCLAIM_TOKEN);
+                    separated.push(' ');
+                } else if c.is_ascii_punctuation() {
+                    separated.push(' ');
+                    separated.push(c);
+                    separated.push(' ');
+                } else {
+                    separated.push(c);
+                }
+            }
+            separated
+        })
+    }
+
+    /// Shortens runs of the same character to at most `max_run` (at least one), so
+    /// elongated words like "freeeee" map back to their base form.
+    pub fn normalize_repeats(self, max_run: usize) -> Self {
+        let max_run = max_run.max(1);
+        self.map_text(|text| {
+            let mut normalized = String::with_capacity(text.len());
+            let mut previous = None;
+            let mut run = 0;
+            for c in text.chars() {
+                if previous == Some(c) {
+                    run += 1;
+                } else {
+                    previous = Some(c);
+                    run = 1;
+                }
+                if run <= max_run {
+                    normalized.push(c);
+                }
+            }
+            normalized
+        })
+    }
+
+    /// Keeps at most the first `max` characters of the message and subject.
+    pub fn truncate_chars(self, max: usize) -> Self {
+        self.map_text(|text| truncate_chars(text, max).to_string())
+    }
+
+    /// Brings visually identical text to the same code points, so that e.g. text
+    /// obfuscated with full-width letters meets the vocabulary. Run it before any
+    /// other step.
+    pub fn normalize_unicode(self, form: UnicodeForm) -> Self {
+        self.map_text(|text| match form {
+            UnicodeForm::Nfc => text.nfc().collect(),
+            UnicodeForm::Nfkc => text.nfkc().collect(),
+        })
+    }
+
+    /// Replaces every number formatted as `format` says with [`NUMBER_TOKEN`], or
+    /// with [`MONEY_TOKEN`] together with a currency symbol right before or after
+    /// it, e.g. "£1,000.00" or "1.000,00€". A number has at most one decimal
+    /// separator and thousands separators only before it, so amounts formatted for
+    /// another locale are split apart. Digits next to letters, as in "call2win", are
+    /// left alone.
+    ///
+    /// Run it before deleting punctuation, which would merge the separators into the
+    /// digits, and tokenize without deleting punctuation to keep the tokens intact.
+    pub fn normalize_numbers(self, format: NumberFormat) -> Self {
+        self.map_text(|text| {
+            let chars = text.chars().collect::<Vec<_>>();
+            let mut normalized = String::with_capacity(text.len());
+            let mut start = 0;
+            while start < chars.len() {
+                let c = chars[start];
+                let preceded_by_word = start > 0 && chars[start - 1].is_alphanumeric();
+                if !c.is_ascii_digit() || preceded_by_word {
+                    normalized.push(c);
+                    start += 1;
+                    continue;
+                }
+
+                let mut end = start;
+                let mut decimal = false;
+                while let Some(&c) = chars.get(end) {
+                    let separator = !decimal && (c == format.decimal || c == format.thousands);
+                    let digit_follows = chars.get(end + 1).is_some_and(char::is_ascii_digit);
+                    if c.is_ascii_digit() {
+                        end += 1;
+                    } else if separator && digit_follows {
+                        decimal = c == format.decimal;
+                        end += 1;
+                    } else {
+                        break;
+                    }
+                }
+                if chars.get(end).is_some_and(|c| c.is_alphanumeric()) {
+                    normalized.extend(&chars[start..end]);
+                    start = end;
+                    continue;
+                }
+
+                let mut money = normalized
+                    .chars()
+                    .next_back()
+                    .is_some_and(|c| CURRENCY_SYMBOLS.contains(&c));
+                if money {
+                    normalized.pop();
+                } else if chars.get(end).is_some_and(|c| CURRENCY_SYMBOLS.contains(c)) {
+                    money = true;
+                    end += 1;
+                }
+                normalized.push_str(if money { MONEY_TOKEN } else { NUMBER_TOKEN });
+                start = end;
+            }
+            normalized
+        })
+    }
+
+    /// Number of fully uppercase words in the subject and the message, e.g. "FREE"
+    /// or "WIN!", which lowercasing erases. Words need at least two letters, so
+    /// that "I" or "A" at the start of a sentence don't count.
+    pub fn all_caps_count(&self) -> usize {
+        self.subject
+            .iter()
+            .flat_map(|subject| subject.split_whitespace())
+            .chain(self.sms.split_whitespace())
+            .filter(|word| {
+                word.chars().filter(|c| c.is_alphabetic()).count() >= 2
+                    && !word.chars().any(char::is_lowercase)
+            })
+            .count()
+    }
+
+    fn map_text<F: Fn(&str) -> String>(self, f: F) -> Self {
         Self {
             label: self.label,
-            sms: self
-                .sms
-                .chars()
-                .filter(|c| !c.is_ascii_punctuation())
-                .collect(),
+            sms: f(&self.sms),
+            subject: self.subject.as_deref().map(&f),
         }
     }
 }
 
+/// The first `max` characters of `text`, to bound the size of pathological inputs.
+pub fn truncate_chars(text: &str, max: usize) -> &str {
+    match text.char_indices().nth(max) {
+        Some((end, _)) => &text[..end],
+        None => text,
+    }
+}
+
+/// Full Unicode case folding of `text`, so caseless matches compare equal where
+/// lowercasing keeps them apart, e.g. "Straße" and "STRASSE" both fold to "strasse".
+///
+/// Folds with the full mappings of the Unicode `CaseFolding.txt` table. With
+/// `turkic`, dotted and dotless I fold the Turkish and Azerbaijani way: "İ" to "i"
+/// and "I" to "ı", instead of "i̇" and "i".
+pub fn casefold(text: &str, turkic: bool) -> String {
+    if !turkic {
+        return caseless::default_case_fold_str(text);
+    }
+    let text = text
+        .chars()
+        .map(|c| match c {
+            'İ' => 'i',
+            'I' => 'ı',
+            c => c,
+        })
+        .collect::<String>();
+    caseless::default_case_fold_str(&text)
+}
+
+/// Parses a single `label<TAB>sms` line.
+fn parse_record<F>(line: &str, parse_label: F) -> Result<RawData, std::io::Error>
+where
+    F: Fn(&str) -> Option<Label>,
+{
+    let (label, sms) = line
+        .split_once('\t')
+        .ok_or_else(|| std::io::Error::new(std::io::ErrorKind::Other, "Missing delimeter"))?;
+    let label = parse_label(label)
+        .ok_or_else(|| std::io::Error::new(std::io::ErrorKind::Other, "Invalid label"))?;
+    Ok(RawData::new(label, sms))
+}
+
+/// Removes the byte order mark some editors save at the start of a file. Only the
+/// first line of a file may carry one.
+fn strip_bom(line: &str) -> &str {
+    line.strip_prefix('\u{feff}').unwrap_or(line)
+}
+
+/// Decodes a file as UTF-8 without its byte order mark, falling back to Latin-1
+/// for files saved in a legacy encoding, where every byte maps to the code point
+/// of the same value.
+fn decode(bytes: &[u8]) -> Cow<'_, str> {
+    let bytes = bytes.strip_prefix(b"\xef\xbb\xbf").unwrap_or(bytes);
+    match std::str::from_utf8(bytes) {
+        Ok(text) => Cow::Borrowed(text),
+        Err(_) => Cow::Owned(bytes.iter().map(|byte| *byte as char).collect()),
+    }
+}
+
+/// Whether every line of `block` is a `Name: value` header, or a continuation of
+/// the previous one indented with whitespace.
+fn is_header_block(block: &str) -> bool {
+    block.lines().enumerate().all(|(index, line)| {
+        if index > 0 && line.starts_with([' ', '\t']) {
+            return true;
+        }
+        match line.split_once(':') {
+            Some((name, _)) => !name.is_empty() && name.bytes().all(|b| b.is_ascii_graphic()),
+            None => false,
+        }
+    })
+}
+
+fn subject_header(line: &str) -> Option<String> {
+    let (name, value) = line.split_once(':')?;
+    if name.trim().eq_ignore_ascii_case("subject") {
+        Some(value.trim().to_string())
+    } else {
+        None
+    }
+}
+
 #[derive(Debug)]
 pub struct RawDataset {
     pub data: Vec<RawData>,
 }
 
 impl RawDataset {
+    /// Loads a tab separated `label\tmessage` file, either UTF-8 with an optional
+    /// byte order mark or Latin-1.
     pub fn from_file<P>(path: P) -> Result<Self, std::io::Error>
     where
         P: AsRef<Path>,
     {
-        let file_data = std::fs::read(path)?;
+        Self::from_file_with_progress(path, &Progress::silent())
+    }
+
+    /// Like [`RawDataset::from_file`], reporting every record to `progress`.
+    fn from_file_with_progress<P: AsRef<Path>>(
+        path: P,
+        progress: &Progress,
+    ) -> Result<Self, std::io::Error> {
+        let bytes = std::fs::read(path)?;
+        let text = decode(&bytes);
+        let lines = text.lines().collect::<Vec<_>>();
+        let data = progress
+            .track(Phase::Loading, lines.into_iter())
+            .map(|line| parse_record(line, |label| Label::from_str(label).ok()))
+            .collect::<Result<Vec<_>, std::io::Error>>()?;
+        Ok(Self { data })
+    }
+
+    /// Reads tab separated `label\tmessage` lines, e.g. from stdin or an in-memory buffer.
+    pub fn from_reader<R: BufRead>(reader: R) -> Result<Self, std::io::Error> {
+        Self::from_reader_with_labels(reader, |label| Label::from_str(label).ok())
+    }
+
+    /// Loads a multi-class file as a binary dataset: records whose label is one of
+    /// `positive` become [`Label::Spam`], every other label becomes [`Label::Ham`].
+    pub fn from_file_one_vs_rest<P>(path: P, positive: &[&str]) -> Result<Self, std::io::Error>
+    where
+        P: AsRef<Path>,
+    {
+        Self::from_reader_with_labels(decode(&std::fs::read(path)?).as_bytes(), |label| {
+            if positive.contains(&label) {
+                Some(Label::Spam)
+            } else {
+                Some(Label::Ham)
+            }
+        })
+    }
+
+    /// Loads a file whose label column uses other strings, e.g. `0`/`1` or
+    /// `legit`/`junk`, mapping each with `labels`. Any other string is an error.
+    pub fn from_file_with_label_map<P>(
+        path: P,
+        labels: &[(&str, Label)],
+    ) -> Result<Self, std::io::Error>
+    where
+        P: AsRef<Path>,
+    {
+        Self::from_reader_with_label_map(decode(&std::fs::read(path)?).as_bytes(), labels)
+    }
+
+    /// Like [`RawDataset::from_file_with_label_map`] for any buffered reader.
+    pub fn from_reader_with_label_map<R: BufRead>(
+        reader: R,
+        labels: &[(&str, Label)],
+    ) -> Result<Self, std::io::Error> {
+        Self::from_reader_with_labels(reader, |label| {
+            labels
+                .iter()
+                .find(|(name, _)| *name == label)
+                .map(|(_, label)| *label)
+        })
+    }
+
+    /// Loads a dataset shipped as two parallel files, one message per line in
+    /// `text_path` and its label on the same line of `label_path`. Fails on the first
+    /// line only one of the files has.
+    pub fn from_text_and_label_files<P, Q>(
+        text_path: P,
+        label_path: Q,
+    ) -> Result<Self, std::io::Error>
+    where
+        P: AsRef<Path>,
+        Q: AsRef<Path>,
+    {
+        let texts = std::fs::read(text_path)?;
+        let labels = std::fs::read(label_path)?;
+        let (texts, labels) = (decode(&texts), decode(&labels));
+        let mut texts = texts.lines();
+        let mut labels = labels.lines();
+
+        let mut data = Vec::new();
+        loop {
+            let line = data.len() + 1;
+            let (sms, label) = match (texts.next(), labels.next()) {
+                (None, None) => break,
+                (Some(sms), Some(label)) => (sms, label),
+                (sms, _) => {
+                    let (short, long) = if sms.is_some() {
+                        ("label", "text")
+                    } else {
+                        ("text", "label")
+                    };
+                    return Err(std::io::Error::new(
+                        std::io::ErrorKind::InvalidData,
+                        format!("{short} file ends before line {line} of the {long} file"),
+                    ));
+                }
+            };
+            let label = Label::from_str(label.trim()).map_err(|_| {
+                std::io::Error::new(
+                    std::io::ErrorKind::InvalidData,
+                    format!("invalid label `{label}` on line {line}"),
+                )
+            })?;
+            data.push(RawData::new(label, sms));
+        }
+        Ok(Self { data })
+    }
 
-        let data = file_data
+    fn from_reader_with_labels<R, F>(reader: R, parse_label: F) -> Result<Self, std::io::Error>
+    where
+        R: BufRead,
+        F: Fn(&str) -> Option<Label>,
+    {
+        let data = reader
             .lines()
-            .map(|line| {
+            .enumerate()
+            .map(|(index, line)| {
                 let line = line?;
-                let (label, sms) = line.split_once('\t').ok_or_else(|| {
-                    std::io::Error::new(std::io::ErrorKind::Other, "Missing delimeter")
-                })?;
-                let label = Label::from_str(label)
-                    .map_err(|_| std::io::Error::new(std::io::ErrorKind::Other, "Invalid label"))?;
-                let sms = sms.to_string();
-                Ok(RawData { label, sms })
+                let line = if index == 0 { strip_bom(&line) } else { &line };
+                parse_record(line, &parse_label)
             })
             .collect::<Result<Vec<_>, std::io::Error>>()?;
         Ok(Self { data })
     }
 
+    /// Loads an email corpus laid out as `ham/` and `spam/` directories with one
+    /// message per file.
+    pub fn from_email_dir<P>(path: P) -> Result<Self, std::io::Error>
+    where
+        P: AsRef<Path>,
+    {
+        let mut data = Vec::new();
+        for (dir, label) in [("ham", Label::Ham), ("spam", Label::Spam)] {
+            let mut paths = std::fs::read_dir(path.as_ref().join(dir))?
+                .map(|entry| entry.map(|entry| entry.path()))
+                .collect::<Result<Vec<_>, std::io::Error>>()?;
+            paths.sort();
+            for path in paths {
+                let email = std::fs::read(path)?;
+                data.push(RawData::from_email(label, &String::from_utf8_lossy(&email)));
+            }
+        }
+        Ok(Self { data })
+    }
+
+    /// Concatenates the records of two datasets, e.g. loaded from different files.
+    pub fn merge(mut self, other: Self) -> Self {
+        self.data.extend(other.data);
+        self
+    }
+
+    /// Messages that appear more than once with different labels, together with
+    /// every label they were given, in order of first appearance.
+    pub fn find_label_conflicts(&self) -> Vec<(String, Vec<Label>)> {
+        let mut order = Vec::new();
+        let mut labels: HashMap<&str, Vec<Label>> = HashMap::new();
+        for row in &self.data {
+            let seen = labels.entry(row.sms.as_str()).or_insert_with(|| {
+                order.push(row.sms.as_str());
+                Vec::new()
+            });
+            if !seen.contains(&row.label) {
+                seen.push(row.label);
+            }
+        }
+        order
+            .into_iter()
+            .filter_map(|sms| {
+                let labels = labels.remove(sms)?;
+                (labels.len() > 1).then(|| (sms.to_string(), labels))
+            })
+            .collect()
+    }
+
     pub fn len(&self) -> usize {
         self.data.len()
     }
@@ -91,20 +703,43 @@ impl RawDataset {
     pub fn is_empty(&self) -> bool {
         self.len() == 0
     }
+
+    /// [`RawData::all_caps_count`] of every record, to take before lowercasing and
+    /// pass to [`Dataset::to_smartcore_with_all_caps`].
+    pub fn all_caps_counts(&self) -> Vec<usize> {
+        self.data.iter().map(RawData::all_caps_count).collect()
+    }
+
     pub fn lowercase(self) -> Self {
-        Self {
-            data: self.data.into_iter().map(|row| row.lowercase()).collect(),
-        }
+        self.apply(&Lowercase)
+    }
+
+    pub fn casefold(self, turkic: bool) -> Self {
+        self.apply(&Casefold { turkic })
     }
 
     pub fn without_punctuaction(self) -> Self {
-        Self {
-            data: self
-                .data
-                .into_iter()
-                .map(|row| row.without_punctuaction())
-                .collect(),
-        }
+        self.apply(&WithoutPunctuation)
+    }
+
+    pub fn separate_punctuation(self, multi_exclaim: bool) -> Self {
+        self.apply(&SeparatePunctuation { multi_exclaim })
+    }
+
+    pub fn normalize_repeats(self, max_run: usize) -> Self {
+        self.apply(&NormalizeRepeats(max_run))
+    }
+
+    pub fn truncate_chars(self, max: usize) -> Self {
+        self.apply(&TruncateChars(max))
+    }
+
+    pub fn normalize_numbers(self, format: NumberFormat) -> Self {
+        self.apply(&NormalizeNumbers(format))
+    }
+
+    pub fn normalize_unicode(self, form: UnicodeForm) -> Self {
+        self.apply(&NormalizeUnicode(form))
     }
 
     pub fn tokenize(self) -> Dataset {
@@ -112,10 +747,20 @@ impl RawDataset {
             .data
             .into_iter()
             .map(|row| {
+                let mut tokens = row
+                    .subject
+                    .iter()
+                    .flat_map(|subject| subject.split_whitespace())
+                    .map(|s| s.to_string())
+                    .collect::<Vec<_>>();
+                let subject_len = tokens.len();
+                tokens.extend(row.sms.split_whitespace().map(|s| s.to_string()));
                 (
                     row.label,
                     TokenizedData {
-                        tokens: row.sms.split_whitespace().map(|s| s.to_string()).collect(),
+                        tokens,
+                        subject_len,
+                        original: None,
                     },
                 )
             })
@@ -131,6 +776,28 @@ pub struct Dataset {
 }
 
 impl Dataset {
+    /// Starts from tokens produced elsewhere, e.g. by an external tokenizer, so only
+    /// the token stages and vectorization are left to run. Fails unless there is one
+    /// label per token list.
+    pub fn from_tokenized<I, S>(labels: Vec<Label>, token_lists: I) -> Result<Self, std::io::Error>
+    where
+        I: IntoIterator,
+        I::Item: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        let data = token_lists
+            .into_iter()
+            .map(|tokens| TokenizedData::new(tokens.into_iter().map(Into::into).collect()))
+            .collect::<Vec<_>>();
+        if data.len() != labels.len() {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                format!("{} labels for {} token lists", labels.len(), data.len()),
+            ));
+        }
+        Ok(Self { labels, data })
+    }
+
     pub fn len(&self) -> usize {
         self.data.len()
     }
@@ -140,84 +807,547 @@ impl Dataset {
     }
 
     pub fn stop_words(self) -> Self {
-        let stops: HashSet<_> = NLTK::stopwords(Language::English).unwrap().iter().collect();
+        self.apply(&StopWords::default())
+    }
+
+    /// Replaces every token of every record with the result of `f`, dropping the
+    /// token when it returns `None`, e.g. for one-off filters or normalizations
+    /// without a dedicated stage. Records are kept, even when left without tokens.
+    pub fn map_tokens<F: Fn(String) -> Option<String>>(self, f: F) -> Self {
         Self {
             labels: self.labels,
             data: self
                 .data
                 .into_iter()
-                .map(|row| TokenizedData {
-                    tokens: row
-                        .tokens
-                        .into_iter()
-                        .filter(|p| !stops.contains(&p.as_str()))
-                        .collect(),
-                })
+                .map(|row| row.filter_map(&f))
                 .collect(),
         }
     }
 
-    pub fn to_smartcore<T: Number>(
-        self,
-    ) -> Result<(DenseMatrix<T>, Vec<T>, HashMap<String, usize>), std::io::Error> {
-        let labels = self
+    /// Like [`Dataset::stop_words`], also returning the stop words removed from each
+    /// record, e.g. to check whether filtering is dropping signal.
+    pub fn stop_words_with_removed(self) -> (Self, Vec<BTreeSet<String>>) {
+        let stop_words = StopWords::default();
+        let (data, removed) = self
+            .data
+            .into_iter()
+            .map(|row| stop_words.apply_with_removed(row))
+            .unzip();
+        (
+            Self {
+                labels: self.labels,
+                data,
+            },
+            removed,
+        )
+    }
+
+    /// Partitions the records into those for which `f` holds and the rest, each
+    /// keeping its label and the original order, e.g. to evaluate the model on the
+    /// messages with a URL only.
+    pub fn split_by<F: Fn(&TokenizedData) -> bool>(self, f: F) -> (Self, Self) {
+        let (matching, rest): (Vec<_>, Vec<_>) = self
             .labels
             .into_iter()
-            .map(|label| match label {
-                Label::Spam => T::one(),
-                Label::Ham => T::zero(),
+            .zip(self.data)
+            .partition(|(_, data)| f(data));
+        let dataset = |rows: Vec<(Label, TokenizedData)>| {
+            let (labels, data) = rows.into_iter().unzip();
+            Self { labels, data }
+        };
+        (dataset(matching), dataset(rest))
+    }
+
+    /// Drops the records left without tokens, e.g. messages made only of stop words,
+    /// whose all-zero rows would teach the model nothing but the class priors.
+    pub fn drop_empty(self) -> Self {
+        self.split_by(|data| !data.tokens.is_empty()).0
+    }
+
+    /// Drops tokens shorter than `min_len` characters.
+    pub fn min_token_len(self, min_len: usize) -> Self {
+        self.apply(&MinTokenLen(min_len))
+    }
+
+    /// Splits tokens at letter/digit boundaries, see [`SplitDigits`].
+    pub fn split_digits(self, keep_original: bool) -> Self {
+        self.apply(&SplitDigits { keep_original })
+    }
+
+    /// Adds skip-bigrams of tokens at most `max_skip` apart, see [`SkipBigrams`].
+    pub fn skip_bigrams(self, max_skip: usize) -> Self {
+        self.apply(&SkipBigrams { max_skip })
+    }
+
+    /// Keeps at most the first `max` tokens of every document.
+    pub fn truncate_tokens(self, max: usize) -> Self {
+        self.apply(&TruncateTokens(max))
+    }
+
+    pub fn to_smartcore<T: Number>(
+        self,
+    ) -> Result<(DenseMatrix<T>, Vec<T>, Vocabulary), std::io::Error> {
+        self.to_smartcore_with_progress(&Progress::silent())
+    }
+
+    /// Like [`Dataset::to_smartcore`], reporting every vectorized record to `progress`.
+    fn to_smartcore_with_progress<T: Number>(
+        self,
+        progress: &Progress,
+    ) -> Result<(DenseMatrix<T>, Vec<T>, Vocabulary), std::io::Error> {
+        let vocabulary = Vocabulary::from_dataset(&self);
+        let (data_m, labels) =
+            self.vectorize(&vocabulary, &LabelEncoder::default(), 1, progress)?;
+        Ok((data_m, labels, vocabulary))
+    }
+
+    /// Like [`Dataset::to_smartcore`], building the vocabulary with `config`.
+    pub fn to_smartcore_with_config<T: Number>(
+        self,
+        config: &VocabConfig,
+    ) -> Result<(DenseMatrix<T>, Vec<T>, Vocabulary), std::io::Error> {
+        let vocabulary = Vocabulary::build(&self, config);
+        let (data_m, labels) = self.to_smartcore_with_vocabulary(&vocabulary)?;
+        Ok((data_m, labels, vocabulary))
+    }
+
+    /// Like [`Dataset::to_smartcore`], encoding the labels with `encoder` instead of
+    /// the default `Spam -> 1, Ham -> 0`.
+    pub fn to_smartcore_with_label_encoder<T: Number>(
+        self,
+        encoder: &LabelEncoder,
+    ) -> Result<(DenseMatrix<T>, Vec<T>, Vocabulary), std::io::Error> {
+        let vocabulary = Vocabulary::from_dataset(&self);
+        let (data_m, labels) = self.vectorize(&vocabulary, encoder, 1, &Progress::silent())?;
+        Ok((data_m, labels, vocabulary))
+    }
+
+    /// Vectorizes the dataset against an existing vocabulary, ignoring unknown tokens.
+    pub fn to_smartcore_with_vocabulary<T: Number>(
+        self,
+        vocabulary: &Vocabulary,
+    ) -> Result<(DenseMatrix<T>, Vec<T>), std::io::Error> {
+        self.vectorize(vocabulary, &LabelEncoder::default(), 1, &Progress::silent())
+    }
+
+    /// The feature vector of the document at `index`, as in the rows of
+    /// [`Dataset::to_smartcore_with_vocabulary`], next to the tokens it was counted
+    /// from, e.g. to check why a message is misclassified. Fails when `index` is out
+    /// of range.
+    pub fn document_features<T: Number>(
+        &self,
+        index: usize,
+        vocabulary: &Vocabulary,
+    ) -> Result<(Vec<T>, &[String]), std::io::Error> {
+        let data = self.data.get(index).ok_or_else(|| {
+            std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                format!("no document {} in a dataset of {}", index, self.len()),
+            )
+        })?;
+        let features = try_subject_weighted_bag_of_words(data, vocabulary, 1)?;
+        Ok((features, &data.tokens))
+    }
+
+    /// Like [`Dataset::to_smartcore`], counting every subject token `subject_weight`
+    /// times, since subject terms tend to be more telling than those of the body.
+    pub fn to_smartcore_with_subject_weight<T: Number>(
+        self,
+        subject_weight: usize,
+    ) -> Result<(DenseMatrix<T>, Vec<T>, Vocabulary), std::io::Error> {
+        let vocabulary = Vocabulary::from_dataset(&self);
+        let (data_m, labels) = self.vectorize(
+            &vocabulary,
+            &LabelEncoder::default(),
+            subject_weight,
+            &Progress::silent(),
+        )?;
+        Ok((data_m, labels, vocabulary))
+    }
+
+    /// Like [`Dataset::to_smartcore`], appending a [`BIAS_TOKEN`] column of ones as
+    /// an explicit intercept for linear models. Messages vectorized later with the
+    /// returned vocabulary need that column set to one as well.
+    pub fn to_smartcore_with_bias<T: Number>(
+        self,
+    ) -> Result<(DenseMatrix<T>, Vec<T>, Vocabulary), std::io::Error> {
+        self.with_feature_column(BIAS_TOKEN, |_| Ok(T::one()))
+    }
+
+    /// Like [`Dataset::to_smartcore`], appending an [`ALL_CAPS_TOKEN`] column with the
+    /// number of fully uppercase words of every document, as counted by
+    /// [`RawDataset::all_caps_counts`] before lowercasing. This keeps the signal of
+    /// shouting without splitting the vocabulary by case. Fails unless there is one
+    /// count per document.
+    pub fn to_smartcore_with_all_caps<T: Number>(
+        self,
+        counts: &[usize],
+    ) -> Result<(DenseMatrix<T>, Vec<T>, Vocabulary), std::io::Error> {
+        if counts.len() != self.len() {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                format!("{} counts for {} documents", counts.len(), self.len()),
+            ));
+        }
+        self.with_feature_column(ALL_CAPS_TOKEN, |row| {
+            T::from_usize(counts[row]).ok_or_else(|| {
+                std::io::Error::new(
+                    std::io::ErrorKind::InvalidData,
+                    format!(
+                        "all caps count {} exceeds the range of the feature type",
+                        counts[row]
+                    ),
+                )
             })
-            .collect::<Vec<_>>();
-        let mut vocabulary = HashMap::new();
-        let mut index = 0;
+        })
+    }
+
+    /// Vectorizes like [`Dataset::to_smartcore`] with one more reserved column
+    /// `token`, holding `value(row)` in every row.
+    fn with_feature_column<T, F>(
+        self,
+        token: &str,
+        value: F,
+    ) -> Result<(DenseMatrix<T>, Vec<T>, Vocabulary), std::io::Error>
+    where
+        T: Number,
+        F: Fn(usize) -> Result<T, std::io::Error>,
+    {
+        let mut vocabulary = Vocabulary::from_dataset(&self);
+        ensure_vocabulary(&vocabulary)?;
+        let column = vocabulary.insert(token);
+        let data = self
+            .data
+            .iter()
+            .enumerate()
+            .map(|(row, data)| {
+                let mut features = try_subject_weighted_bag_of_words::<T>(data, &vocabulary, 1)?;
+                features[column] = value(row)?;
+                Ok(features)
+            })
+            .collect::<Result<Vec<_>, std::io::Error>>()?;
+        let labels = LabelEncoder::default().encode_all(self.labels);
+        Ok((DenseMatrix::from_2d_vec(&data), labels, vocabulary))
+    }
+
+    fn vectorize<T: Number>(
+        self,
+        vocabulary: &Vocabulary,
+        encoder: &LabelEncoder,
+        subject_weight: usize,
+        progress: &Progress,
+    ) -> Result<(DenseMatrix<T>, Vec<T>), std::io::Error> {
+        ensure_vocabulary(vocabulary)?;
+        let labels = encoder.encode_all(self.labels);
+
+        let data = progress
+            .track(Phase::Vectorizing, self.data.iter())
+            .map(|data| try_subject_weighted_bag_of_words::<T>(data, vocabulary, subject_weight))
+            .collect::<Result<Vec<_>, std::io::Error>>()?;
 
-        for word in self.data.clone().into_iter().flat_map(|data| data.tokens) {
-            if let Entry::Vacant(entry) = vocabulary.entry(word) {
-                entry.insert(index);
-                index += 1;
+        let data_m = DenseMatrix::from_2d_vec(&data);
+
+        Ok((data_m, labels))
+    }
+
+    /// TF-IDF weighted features with smoothed `idf = ln((1 + n) / (1 + df)) + 1`,
+    /// each row scaled to unit L2 norm.
+    pub fn to_tfidf(self) -> Result<(DenseMatrix<f64>, Vec<usize>, Vocabulary), std::io::Error> {
+        self.to_weighted(TermWeighting::default())
+    }
+
+    /// Real-valued features weighted as configured by `weighting`.
+    pub fn to_weighted(
+        self,
+        weighting: TermWeighting,
+    ) -> Result<(DenseMatrix<f64>, Vec<usize>, Vocabulary), std::io::Error> {
+        let frequencies = DocumentFrequencies::from_dataset(&self);
+        let vocabulary =
+            Vocabulary::from_document_frequencies(&frequencies, &VocabConfig::default());
+        ensure_vocabulary(&vocabulary)?;
+        let labels = LabelEncoder::default().encode_all(self.labels);
+
+        let n = frequencies.documents() as f64;
+        let mut idf = vec![1.0; vocabulary.len()];
+        if weighting.idf {
+            for (token, index) in vocabulary.iter() {
+                idf[index] = ((1.0 + n) / (1.0 + frequencies.get(token) as f64)).ln() + 1.0;
             }
         }
 
-        let data = self
+        let counts = self
             .data
             .into_iter()
-            .map(|data| bag_of_words::<T>(data.tokens, &vocabulary))
+            .map(|data| bag_of_words_iter::<f64, _>(data.tokens, &vocabulary))
             .collect::<Vec<_>>();
 
-        let data_m = DenseMatrix::from_2d_vec(&data);
+        let data = counts
+            .into_iter()
+            .map(|row| {
+                let row = row
+                    .iter()
+                    .zip(&idf)
+                    .map(|(count, idf)| {
+                        let tf = if weighting.sublinear_tf {
+                            sublinear_tf(*count)
+                        } else {
+                            *count
+                        };
+                        tf * idf
+                    })
+                    .collect::<Vec<_>>();
+                let norm = row.iter().map(|x| x * x).sum::<f64>().sqrt();
+                if weighting.l2_normalize && norm > 0.0 {
+                    row.into_iter().map(|x| x / norm).collect()
+                } else {
+                    row
+                }
+            })
+            .collect::<Vec<_>>();
 
-        Ok((data_m, labels, vocabulary))
+        Ok((DenseMatrix::from_2d_vec(&data), labels, vocabulary))
+    }
+}
+
+/// How [`Dataset::to_weighted`] turns token counts into features. The default is
+/// the TF-IDF of [`Dataset::to_tfidf`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TermWeighting {
+    /// Replaces non-zero counts by `1 + ln(count)`, see [`sublinear_tf`].
+    pub sublinear_tf: bool,
+    /// Multiplies by the smoothed inverse document frequency.
+    pub idf: bool,
+    /// Scales every row to unit L2 norm.
+    pub l2_normalize: bool,
+}
+
+impl Default for TermWeighting {
+    fn default() -> Self {
+        Self {
+            sublinear_tf: false,
+            idf: true,
+            l2_normalize: true,
+        }
+    }
+}
+
+/// `1 + ln(count)` for a non-zero count, so repeating a token adds less and less
+/// weight, and `0` otherwise.
+pub fn sublinear_tf(count: f64) -> f64 {
+    if count > 0.0 {
+        1.0 + count.ln()
+    } else {
+        0.0
     }
 }
 
+/// Fails with [`DataCleanError::EmptyVocabulary`] rather than building matrices
+/// without columns, which smartcore cannot train on.
+fn ensure_vocabulary(vocabulary: &Vocabulary) -> Result<(), std::io::Error> {
+    if vocabulary.is_empty() {
+        return Err(DataCleanError::EmptyVocabulary.into());
+    }
+    Ok(())
+}
+
+fn preprocess_file<P: AsRef<Path>>(path: P) -> Result<Dataset, std::io::Error> {
+    preprocess_file_with_progress(path, &Progress::silent())
+}
+
+fn preprocess_file_with_progress<P: AsRef<Path>>(
+    path: P,
+    progress: &Progress,
+) -> Result<Dataset, std::io::Error> {
+    Ok(RawDataset::from_file_with_progress(path, progress)?
+        .tokenize_with_progress(&Tokenizer::standard(), progress))
+}
+
+/// Features and labels of a labeled SMS file, tokenized with
+/// [`Tokenizer::standard`], the input the bundled model is trained on. Records left
+/// without tokens are dropped, see [`Dataset::drop_empty`].
 pub fn create_smartcore_input<T: Number, P: AsRef<Path>>(
     path: P,
-) -> Result<(DenseMatrix<T>, Vec<T>, HashMap<String, usize>), std::io::Error> {
-    RawDataset::from_file(path)
-        .expect("creation failed")
-        .lowercase()
-        .without_punctuaction()
-        .tokenize()
-        .stop_words()
-        .to_smartcore()
+) -> Result<(DenseMatrix<T>, Vec<T>, Vocabulary), std::io::Error> {
+    smartcore_input(path, &Progress::silent())
 }
 
-pub fn bag_of_words<T: Number>(tokens: Vec<String>, vocabulary: &HashMap<String, usize>) -> Vec<T> {
-    let mut m = Vec::zeros(vocabulary.len());
+/// The steps of [`create_smartcore_input`], shared with
+/// [`create_smartcore_input_with_progress`].
+fn smartcore_input<T: Number, P: AsRef<Path>>(
+    path: P,
+    progress: &Progress,
+) -> Result<(DenseMatrix<T>, Vec<T>, Vocabulary), std::io::Error> {
+    preprocess_file_with_progress(path, progress)?
+        .drop_empty()
+        .to_smartcore_with_progress(progress)
+}
+
+/// Word counts for count-based models such as `MultinomialNB` or `CategoricalNB`.
+pub fn create_counts_input<P: AsRef<Path>>(
+    path: P,
+) -> Result<(DenseMatrix<usize>, Vec<usize>, Vocabulary), std::io::Error> {
+    create_smartcore_input(path)
+}
+
+/// TF-IDF features for models working on real-valued inputs, e.g. `GaussianNB`,
+/// `LogisticRegression` or `SVC`. Not suitable for `MultinomialNB`, which expects counts.
+/// Like [`create_smartcore_input`], records left without tokens are dropped.
+pub fn create_tfidf_input<P: AsRef<Path>>(
+    path: P,
+) -> Result<(DenseMatrix<f64>, Vec<usize>, Vocabulary), std::io::Error> {
+    preprocess_file(path)?.drop_empty().to_tfidf()
+}
 
+/// Counts of every token of a plain token to column map in `tokens`, see
+/// [`bag_of_words_iter`] for a [`Vocabulary`].
+///
+/// Panics if a count does not fit in `T`, see [`try_bag_of_words`], or if a column
+/// is not below `vocabulary.len()`.
+pub fn bag_of_words<T: Number>(tokens: Vec<String>, vocabulary: &HashMap<String, usize>) -> Vec<T> {
+    let mut counts = vec![0usize; vocabulary.len()];
     for token in tokens {
         if let Some(index) = vocabulary.get(&token) {
-            m.add_element_mut(*index, T::one());
+            counts[*index] += 1;
         }
     }
+    to_features(counts).unwrap_or_else(|err| panic!("{}", err))
+}
+
+/// Like [`bag_of_words_iter`] for owned tokens, but returns an error instead of
+/// overflowing when a token repeats more often than the feature type can count,
+/// e.g. with `u8` features.
+pub fn try_bag_of_words<T: Number>(
+    tokens: Vec<String>,
+    vocabulary: &Vocabulary,
+) -> Result<Vec<T>, std::io::Error> {
+    try_bag_of_words_iter(tokens, vocabulary)
+}
 
-    m
+/// Counts of every vocabulary token in `tokens`, e.g. a `Vec<String>`, a `&[String]`
+/// or the `&str` pieces of a message, without allocating them.
+///
+/// Panics if a count does not fit in `T`, see [`try_bag_of_words_iter`].
+pub fn bag_of_words_iter<T, I>(tokens: I, vocabulary: &Vocabulary) -> Vec<T>
+where
+    T: Number,
+    I: IntoIterator,
+    I::Item: AsRef<str>,
+{
+    try_bag_of_words_iter(tokens, vocabulary).unwrap_or_else(|err| panic!("{}", err))
+}
+
+/// Like [`try_bag_of_words`] for borrowed tokens.
+pub fn try_bag_of_words_iter<T, I>(
+    tokens: I,
+    vocabulary: &Vocabulary,
+) -> Result<Vec<T>, std::io::Error>
+where
+    T: Number,
+    I: IntoIterator,
+    I::Item: AsRef<str>,
+{
+    try_bag_of_words_with_mode(tokens, vocabulary, CountMode::Count)
+}
+
+/// How a token repeated within a document is counted.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum CountMode {
+    /// Number of occurrences, what `MultinomialNB` is trained on.
+    #[default]
+    Count,
+    /// `1` whenever the token occurs, for presence-based analyses.
+    Binary,
+}
+
+/// Like [`bag_of_words_iter`], counting repeated tokens as configured by `mode`.
+pub fn bag_of_words_with_mode<T, I>(tokens: I, vocabulary: &Vocabulary, mode: CountMode) -> Vec<T>
+where
+    T: Number,
+    I: IntoIterator,
+    I::Item: AsRef<str>,
+{
+    try_bag_of_words_with_mode(tokens, vocabulary, mode).unwrap_or_else(|err| panic!("{}", err))
+}
+
+/// Like [`try_bag_of_words_iter`], counting repeated tokens as configured by `mode`.
+pub fn try_bag_of_words_with_mode<T, I>(
+    tokens: I,
+    vocabulary: &Vocabulary,
+    mode: CountMode,
+) -> Result<Vec<T>, std::io::Error>
+where
+    T: Number,
+    I: IntoIterator,
+    I::Item: AsRef<str>,
+{
+    let mut counts = vec![0usize; vocabulary.len()];
+    add_counts(&mut counts, tokens, vocabulary, 1);
+    if mode == CountMode::Binary {
+        counts.iter_mut().for_each(|count| *count = (*count).min(1));
+    }
+    to_features(counts)
+}
+
+/// Like [`try_bag_of_words`], counting every subject token of `data` `subject_weight`
+/// times.
+pub fn try_subject_weighted_bag_of_words<T: Number>(
+    data: &TokenizedData,
+    vocabulary: &Vocabulary,
+    subject_weight: usize,
+) -> Result<Vec<T>, std::io::Error> {
+    let mut counts = vec![0usize; vocabulary.len()];
+    add_counts(
+        &mut counts,
+        data.subject_tokens(),
+        vocabulary,
+        subject_weight,
+    );
+    add_counts(&mut counts, data.body_tokens(), vocabulary, 1);
+    to_features(counts)
+}
+
+fn add_counts<I>(counts: &mut [usize], tokens: I, vocabulary: &Vocabulary, weight: usize)
+where
+    I: IntoIterator,
+    I::Item: AsRef<str>,
+{
+    for token in tokens {
+        if let Some(index) = vocabulary.get(token.as_ref()) {
+            counts[index] += weight;
+        }
+    }
+}
+
+fn to_features<T: Number>(counts: Vec<usize>) -> Result<Vec<T>, std::io::Error> {
+    counts
+        .into_iter()
+        .enumerate()
+        .map(|(index, count)| {
+            T::from_usize(count).ok_or_else(|| {
+                std::io::Error::new(
+                    std::io::ErrorKind::InvalidData,
+                    format!(
+                        "count {count} of feature {index} exceeds the range of the feature type"
+                    ),
+                )
+            })
+        })
+        .collect()
 }
 
 #[cfg(test)]
 mod test {
+    use std::collections::HashMap;
 
-    use crate::RawDataset;
+    use smartcore::linalg::basic::arrays::Array;
+
+    use crate::{
+        bag_of_words, bag_of_words_iter, bag_of_words_with_mode, casefold, create_counts_input,
+        create_tfidf_input, matrix_rows, preprocess_file, sublinear_tf, truncate_chars,
+        try_bag_of_words, CountMode, DataCleanError, Dataset, Label, NumberFormat, RawData,
+        RawDataset, TermWeighting, Tokenizer, UnicodeForm, Vocabulary, ALL_CAPS_TOKEN, BIAS_TOKEN,
+        MULTI_EXCLAIM_TOKEN,
+    };
 
     #[test]
     fn test_file_load() {
@@ -251,4 +1381,625 @@ mod test {
             .to_smartcore::<f64>()
             .expect("Failed to convert to smartcore");
     }
+
+    #[test]
+    fn test_min_token_len() {
+        let dataset = RawDataset {
+            data: vec![RawData::new(Label::Spam, "ok hi free")],
+        }
+        .tokenize()
+        .min_token_len(3);
+        assert_eq!(dataset.data[0].tokens, vec!["free".to_string()]);
+    }
+
+    #[test]
+    fn test_casefold() {
+        assert_eq!("İstanbul".to_lowercase(), "i\u{307}stanbul");
+        assert_eq!(casefold("İstanbul", false), "i\u{307}stanbul");
+        assert_eq!(casefold("İSTANBUL", true), "istanbul");
+        assert_eq!(casefold("Straße", false), casefold("STRASSE", false));
+        // Folds to two characters, while lowercasing keeps it as is.
+        assert_eq!(casefold("\u{149}", false), "\u{2bc}n");
+
+        let dataset = RawDataset {
+            data: vec![RawData::new(Label::Ham, "İyi geceler")],
+        };
+        assert_eq!(dataset.casefold(true).data[0].sms, "iyi geceler");
+    }
+
+    #[test]
+    fn test_skip_bigrams() {
+        let tokens = |max_skip| {
+            let dataset = RawDataset {
+                data: vec![RawData::new(Label::Spam, "call me now")],
+            };
+            let mut dataset = dataset.tokenize().skip_bigrams(max_skip);
+            dataset.data.remove(0).tokens
+        };
+        assert_eq!(
+            tokens(1),
+            vec!["call", "me", "now", "call_me", "call_now", "me_now"]
+        );
+        assert!(!tokens(0).contains(&"call_now".to_string()));
+    }
+
+    #[test]
+    fn test_split_digits() {
+        let tokens = |keep_original| {
+            let dataset = RawDataset {
+                data: vec![RawData::new(Label::Spam, "call2win £1000 now")],
+            };
+            let mut dataset = dataset.tokenize().split_digits(keep_original);
+            dataset.data.remove(0).tokens
+        };
+        assert_eq!(tokens(false), vec!["call", "2", "win", "£", "1000", "now"]);
+        assert_eq!(tokens(true)[..4], ["call2win", "call", "2", "win"]);
+    }
+
+    #[test]
+    fn test_subject_weight() {
+        let email = "Subject: free prize\n\nfree lunch";
+        let dataset = RawDataset {
+            data: vec![RawData::from_email(Label::Spam, email)],
+        }
+        .tokenize()
+        .stop_words();
+        assert_eq!(dataset.data[0].subject_tokens(), ["free", "prize"]);
+        assert_eq!(dataset.data[0].body_tokens(), ["free", "lunch"]);
+
+        let (x, _, vocabulary) = dataset
+            .to_smartcore_with_subject_weight::<usize>(3)
+            .expect("failed to vectorize");
+        let count = |token: &str| *x.get((0, vocabulary.get(token).unwrap()));
+        assert_eq!(count("prize"), 3);
+        assert_eq!(count("lunch"), 1);
+        assert_eq!(count("free"), 4);
+    }
+
+    #[test]
+    fn test_bias_column() {
+        let dataset = RawDataset {
+            data: vec![
+                RawData::new(Label::Spam, "free prize"),
+                RawData::new(Label::Ham, "lunch later"),
+                RawData::new(Label::Ham, ""),
+            ],
+        }
+        .tokenize();
+        let tokens = Vocabulary::from_dataset(&dataset).len();
+
+        let (x, y, vocabulary) = dataset
+            .to_smartcore_with_bias::<usize>()
+            .expect("failed to vectorize");
+        assert_eq!(vocabulary.len(), tokens + 1);
+        assert_eq!(x.shape(), (3, tokens + 1));
+        let bias = vocabulary.get(BIAS_TOKEN).unwrap();
+        assert_eq!(bias, tokens);
+        assert!((0..3).all(|row| *x.get((row, bias)) == 1));
+        assert_eq!(*x.get((0, vocabulary.get("free").unwrap())), 1);
+        assert_eq!(y, vec![1, 0, 0]);
+    }
+
+    #[test]
+    fn test_all_caps_feature() {
+        let dataset = RawDataset {
+            data: vec![
+                RawData::new(Label::Spam, "I said WIN a FREE prize, Win now!"),
+                RawData::new(Label::Ham, "see you at 6"),
+            ],
+        };
+        let counts = dataset.all_caps_counts();
+        assert_eq!(counts, vec![2, 0]);
+
+        let (x, _, vocabulary) = dataset
+            .lowercase()
+            .without_punctuaction()
+            .tokenize()
+            .to_smartcore_with_all_caps::<usize>(&counts)
+            .expect("failed to vectorize");
+        let column = vocabulary.get(ALL_CAPS_TOKEN).unwrap();
+        assert_eq!(column, vocabulary.len() - 1);
+        assert_eq!(*x.get((0, column)), 2);
+        assert_eq!(*x.get((1, column)), 0);
+        assert_eq!(*x.get((0, vocabulary.get("win").unwrap())), 2);
+        assert!(!vocabulary.contains("WIN"));
+
+        let dataset = RawDataset {
+            data: vec![RawData::new(Label::Spam, "FREE")],
+        };
+        assert!(dataset
+            .tokenize()
+            .to_smartcore_with_all_caps::<usize>(&[])
+            .is_err());
+    }
+
+    #[test]
+    fn test_normalize_numbers() {
+        let normalize = |text: &str, format| {
+            RawData::new(Label::Spam, text)
+                .normalize_numbers(format)
+                .sms
+        };
+        assert_eq!(
+            normalize("Win £1,000.00 now", NumberFormat::US),
+            "Win __MONEY__ now"
+        );
+        assert_eq!(
+            normalize("Win 1.000,00€ now", NumberFormat::EUROPEAN),
+            "Win __MONEY__ now"
+        );
+        assert_eq!(
+            normalize("Call 08712 300 by 5.30", NumberFormat::US),
+            "Call __NUM__ __NUM__ by __NUM__"
+        );
+        assert_eq!(
+            normalize("Win £1.000,00", NumberFormat::US),
+            "Win __MONEY__,__NUM__"
+        );
+        assert_eq!(normalize("call2win 4u", NumberFormat::US), "call2win 4u");
+    }
+
+    #[test]
+    fn test_normalize_unicode() {
+        let normalize =
+            |text: &str, form| RawData::new(Label::Spam, text).normalize_unicode(form).sms;
+        assert_eq!(normalize("ＦＲＥＥ ﬁlm", UnicodeForm::Nfkc), "FREE film");
+        assert_eq!(normalize("ＦＲＥＥ", UnicodeForm::Nfc), "ＦＲＥＥ");
+        assert_eq!(normalize("cafe\u{301}", UnicodeForm::Nfc), "caf\u{e9}");
+
+        let dataset = RawDataset {
+            data: vec![RawData::new(Label::Spam, "ＦＲＥＥ prize")],
+        };
+        let dataset = dataset
+            .normalize_unicode(UnicodeForm::Nfkc)
+            .lowercase()
+            .tokenize();
+        assert_eq!(dataset.data[0].tokens, vec!["free", "prize"]);
+    }
+
+    #[test]
+    fn test_email() {
+        let email = "From: promo@example.com\r\nSubject: You WON a prize!\r\n\r\nClaim your prize today.\r\n";
+        let row = RawData::from_email(Label::Spam, email);
+        assert_eq!(row.subject.as_deref(), Some("You WON a prize!"));
+        assert_eq!(row.sms, "Claim your prize today.");
+
+        let dataset = RawDataset { data: vec![row] }
+            .lowercase()
+            .without_punctuaction()
+            .tokenize();
+        assert_eq!(
+            dataset.data[0].tokens,
+            vec!["you", "won", "a", "prize", "claim", "your", "prize", "today"]
+        );
+
+        let folded = "Subject: You WON\n  a prize!\nFrom: promo@example.com\n\nClaim it.";
+        assert_eq!(RawData::from_email(Label::Spam, folded).sms, "Claim it.");
+    }
+
+    #[test]
+    fn test_email_without_headers() {
+        let email = "Hi John,\nyou won a free prize!\n\nClaim it today.";
+        let row = RawData::from_email(Label::Spam, email);
+        assert_eq!(row.subject, None);
+        assert_eq!(row.sms, email);
+    }
+
+    #[test]
+    fn test_find_label_conflicts() {
+        let first = RawDataset {
+            data: vec![
+                RawData::new(Label::Ham, "see you tonight"),
+                RawData::new(Label::Spam, "claim your prize"),
+            ],
+        };
+        let second = RawDataset {
+            data: vec![
+                RawData::new(Label::Spam, "see you tonight"),
+                RawData::new(Label::Spam, "claim your prize"),
+            ],
+        };
+        let conflicts = first.merge(second).find_label_conflicts();
+        assert_eq!(
+            conflicts,
+            vec![("see you tonight".to_string(), vec![Label::Ham, Label::Spam])]
+        );
+    }
+
+    #[test]
+    fn test_create_counts_input() {
+        let (matrix, labels, vocab) =
+            create_counts_input("../../SMSSpamCollection").expect("failed to init");
+        // Messages such as ":) " have no tokens left and are dropped.
+        let rows = preprocess_file("../../SMSSpamCollection")
+            .expect("failed to load")
+            .drop_empty()
+            .len();
+        assert!(rows < 5574);
+        assert_eq!(matrix.shape(), (rows, vocab.len()));
+        assert_eq!(labels.len(), rows);
+        assert!(labels.iter().all(|label| *label <= 1));
+    }
+
+    #[test]
+    fn test_create_tfidf_input() {
+        let (matrix, labels, vocab) =
+            create_tfidf_input("../../SMSSpamCollection").expect("failed to init");
+        let (rows, cols) = matrix.shape();
+        let expected_rows = preprocess_file("../../SMSSpamCollection")
+            .expect("failed to load")
+            .drop_empty()
+            .len();
+        assert_eq!((rows, cols), (expected_rows, vocab.len()));
+        assert_eq!(labels.len(), expected_rows);
+        let norm = (0..cols)
+            .map(|col| matrix.get((0, col)).powi(2))
+            .sum::<f64>()
+            .sqrt();
+        assert!((norm - 1.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_sublinear_tf() {
+        let dataset = RawDataset {
+            data: vec![
+                RawData::new(Label::Spam, "free free free prize"),
+                RawData::new(Label::Ham, "lunch"),
+            ],
+        }
+        .tokenize();
+        let weighting = TermWeighting {
+            sublinear_tf: true,
+            idf: false,
+            l2_normalize: false,
+        };
+        let (matrix, _, vocabulary) = dataset.to_weighted(weighting).expect("failed to weight");
+
+        let free = vocabulary.get("free").unwrap();
+        let prize = vocabulary.get("prize").unwrap();
+        assert!((matrix.get((0, free)) - (1.0 + 3f64.ln())).abs() < 1e-12);
+        assert_eq!(*matrix.get((0, prize)), 1.0);
+        assert_eq!(*matrix.get((1, free)), 0.0);
+        assert_eq!(sublinear_tf(0.0), 0.0);
+    }
+
+    #[test]
+    fn test_from_tokenized() {
+        let dataset = Dataset::from_tokenized(
+            vec![Label::Spam, Label::Ham],
+            vec![vec!["free", "the", "prize"], vec!["see", "you", "lunch"]],
+        )
+        .expect("failed to build")
+        .stop_words();
+        assert_eq!(dataset.data[0].tokens, vec!["free", "prize"]);
+
+        let (matrix, labels, vocabulary) = dataset.to_smartcore::<usize>().expect("failed");
+        assert_eq!(vocabulary.len(), 4);
+        assert_eq!(matrix.shape(), (2, 4));
+        assert_eq!(*matrix.get((1, vocabulary.get("lunch").unwrap())), 1);
+        assert_eq!(labels, vec![1, 0]);
+
+        assert!(Dataset::from_tokenized(vec![Label::Ham], Vec::<Vec<String>>::new()).is_err());
+    }
+
+    #[test]
+    fn test_empty_vocabulary() {
+        let dataset = Dataset::from_tokenized(
+            vec![Label::Spam, Label::Ham],
+            vec![vec!["the", "a"], vec!["is", "it", "an"]],
+        )
+        .expect("failed to build")
+        .stop_words();
+
+        let err = dataset.to_smartcore::<usize>().unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+        assert_eq!(
+            DataCleanError::from_io(&err),
+            Some(&DataCleanError::EmptyVocabulary)
+        );
+    }
+
+    #[test]
+    fn test_try_bag_of_words_overflow() {
+        let mut vocabulary = Vocabulary::new();
+        vocabulary.insert("free");
+        let tokens = vec!["free".to_string(); 300];
+
+        assert!(try_bag_of_words::<u8>(tokens.clone(), &vocabulary).is_err());
+        assert_eq!(
+            try_bag_of_words::<u16>(tokens, &vocabulary).expect("fits in u16"),
+            vec![300]
+        );
+    }
+
+    #[test]
+    fn test_bag_of_words_iter() {
+        let vocabulary = Vocabulary::from_dataset(
+            &RawDataset {
+                data: vec![RawData::new(Label::Spam, "win free cash")],
+            }
+            .tokenize(),
+        );
+        let tokens = vec!["free".to_string(), "cash".to_string(), "free".to_string()];
+
+        let borrowed = bag_of_words_iter::<usize, _>(&tokens, &vocabulary);
+        assert_eq!(borrowed, vec![0, 2, 1]);
+        assert_eq!(
+            bag_of_words_iter::<usize, _>("free cash free".split(' '), &vocabulary),
+            borrowed
+        );
+        let map = vocabulary
+            .iter()
+            .map(|(token, index)| (token.to_string(), index))
+            .collect::<HashMap<_, _>>();
+        assert_eq!(bag_of_words::<usize>(tokens, &map), borrowed);
+    }
+
+    #[test]
+    fn test_count_mode() {
+        let vocabulary = Vocabulary::from_dataset(
+            &RawDataset {
+                data: vec![RawData::new(Label::Spam, "win free cash")],
+            }
+            .tokenize(),
+        );
+        let tokens = "free cash free free".split(' ');
+
+        assert_eq!(
+            bag_of_words_with_mode::<usize, _>(tokens.clone(), &vocabulary, CountMode::Count),
+            vec![0, 3, 1]
+        );
+        assert_eq!(
+            bag_of_words_with_mode::<usize, _>(tokens, &vocabulary, CountMode::Binary),
+            vec![0, 1, 1]
+        );
+    }
+
+    #[test]
+    fn test_from_file_one_vs_rest() {
+        let path = std::env::temp_dir().join("sms-data-clean-one-vs-rest.tsv");
+        std::fs::write(
+            &path,
+            "spam\tWin a prize\npromo\t20% off today\nham\tSee you at six\n",
+        )
+        .expect("failed to write fixture");
+
+        let dataset = RawDataset::from_file_one_vs_rest(&path, &["spam"]).expect("creation failed");
+        let labels = dataset.data.iter().map(|row| row.label).collect::<Vec<_>>();
+        assert_eq!(labels, vec![Label::Spam, Label::Ham, Label::Ham]);
+
+        let dataset =
+            RawDataset::from_file_one_vs_rest(&path, &["spam", "promo"]).expect("creation failed");
+        let labels = dataset.data.iter().map(|row| row.label).collect::<Vec<_>>();
+        assert_eq!(labels, vec![Label::Spam, Label::Spam, Label::Ham]);
+        assert!(RawDataset::from_file(&path).is_err());
+    }
+
+    #[test]
+    fn test_from_file_with_label_map() {
+        let path = std::env::temp_dir().join("sms-data-clean-label-map.tsv");
+        std::fs::write(&path, "1\tWin a prize\n0\tSee you at six\n").expect("failed to write");
+
+        let labels = [("0", Label::Ham), ("1", Label::Spam)];
+        let dataset =
+            RawDataset::from_file_with_label_map(&path, &labels).expect("creation failed");
+        let labels = dataset.data.iter().map(|row| row.label).collect::<Vec<_>>();
+        assert_eq!(labels, vec![Label::Spam, Label::Ham]);
+        assert_eq!(dataset.data[0].sms, "Win a prize");
+
+        assert!(RawDataset::from_file(&path).is_err());
+        let input = std::io::Cursor::new(b"ham\tSee you at six\n");
+        assert!(RawDataset::from_reader_with_label_map(input, &[("0", Label::Ham)]).is_err());
+    }
+
+    #[test]
+    fn test_from_text_and_label_files() {
+        let dir = std::env::temp_dir().join("sms-data-clean-parallel");
+        std::fs::create_dir_all(&dir).expect("failed to create temp dir");
+        let texts = dir.join("texts.txt");
+        let labels = dir.join("labels.txt");
+        std::fs::write(&texts, "Win a prize\nSee you at six\nFree cash now\n")
+            .expect("failed to write");
+        std::fs::write(&labels, "spam\nham\nspam").expect("failed to write");
+
+        let dataset =
+            RawDataset::from_text_and_label_files(&texts, &labels).expect("creation failed");
+        let labels_read = dataset.data.iter().map(|row| row.label).collect::<Vec<_>>();
+        assert_eq!(labels_read, vec![Label::Spam, Label::Ham, Label::Spam]);
+        assert_eq!(dataset.data[1].sms, "See you at six");
+
+        std::fs::write(&labels, "spam\nham\n").expect("failed to write");
+        let err = RawDataset::from_text_and_label_files(&texts, &labels).unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+        assert_eq!(
+            err.to_string(),
+            "label file ends before line 3 of the text file"
+        );
+    }
+
+    #[test]
+    fn test_from_reader() {
+        let input = std::io::Cursor::new(b"ham\tSee you at six\nspam\tWin a prize now\n");
+        let dataset = RawDataset::from_reader(input).expect("creation failed");
+        assert_eq!(dataset.len(), 2);
+        assert_eq!(dataset.data[0].label, Label::Ham);
+        assert_eq!(dataset.data[1].sms, "Win a prize now");
+    }
+
+    #[test]
+    fn test_from_file_bom_and_latin1() {
+        let dir = std::env::temp_dir().join("sms-data-clean-encoding");
+        std::fs::create_dir_all(&dir).expect("failed to create temp dir");
+
+        let bom = dir.join("bom.tsv");
+        std::fs::write(&bom, "\u{feff}ham\tSee you at six\nspam\tWin now\n")
+            .expect("failed to write file");
+        let dataset = RawDataset::from_file(&bom).expect("creation failed");
+        assert_eq!(dataset.len(), 2);
+        assert_eq!(dataset.data[0].label, Label::Ham);
+
+        let input = std::io::Cursor::new("\u{feff}ham\tSee you at six\n\u{feff}spam\tWin now\n");
+        assert!(RawDataset::from_reader(input).is_err());
+
+        let latin1 = dir.join("latin1.tsv");
+        std::fs::write(&latin1, b"spam\tWin 100\xa3 now\n").expect("failed to write file");
+        let dataset = RawDataset::from_file(&latin1).expect("creation failed");
+        assert_eq!(dataset.data[0].sms, "Win 100\u{a3} now");
+    }
+
+    #[test]
+    fn test_separate_punctuation() {
+        let tokens = |multi_exclaim| {
+            let dataset = RawDataset {
+                data: vec![RawData::new(Label::Spam, "Win!!! now, ok!")],
+            };
+            let mut dataset = dataset.separate_punctuation(multi_exclaim).tokenize();
+            dataset.data.remove(0).tokens
+        };
+        assert_eq!(
+            tokens(true),
+            vec!["Win", MULTI_EXCLAIM_TOKEN, "now", ",", "ok", "!"]
+        );
+        assert_eq!(
+            tokens(false),
+            vec!["Win", "!", "!", "!", "now", ",", "ok", "!"]
+        );
+    }
+
+    #[test]
+    fn test_normalize_repeats() {
+        let dataset = RawDataset {
+            data: vec![
+                RawData::new(Label::Spam, "wiiiin"),
+                RawData::new(Label::Spam, "win"),
+                RawData::new(Label::Spam, "freeeee"),
+            ],
+        };
+        let dataset = dataset.normalize_repeats(1).tokenize();
+        assert_eq!(dataset.data[0].tokens, dataset.data[1].tokens);
+        assert_eq!(dataset.data[0].tokens, vec!["win"]);
+
+        let row = RawData::new(Label::Spam, "freeeee wiiiin").normalize_repeats(2);
+        assert_eq!(row.sms, "free wiin");
+    }
+
+    #[test]
+    fn test_truncate() {
+        let long = "free ".repeat(10_000);
+        let dataset = RawDataset {
+            data: vec![RawData::new(Label::Spam, long.as_str())],
+        };
+        let dataset = dataset.truncate_chars(100);
+        assert_eq!(dataset.data[0].sms.chars().count(), 100);
+
+        let dataset = dataset.tokenize();
+        assert_eq!(dataset.data[0].tokens.len(), 20);
+        let dataset = dataset.truncate_tokens(5);
+        assert_eq!(dataset.data[0].tokens.len(), 5);
+
+        assert_eq!(truncate_chars("héllo wörld", 4), "héll");
+        assert_eq!(truncate_chars("hi", 4), "hi");
+    }
+
+    #[test]
+    fn test_document_features() {
+        let dataset = RawDataset {
+            data: vec![
+                RawData::new(Label::Spam, "free prize free cash"),
+                RawData::new(Label::Ham, "see you for lunch"),
+            ],
+        }
+        .tokenize();
+        let vocabulary = Vocabulary::from_dataset(&dataset);
+
+        let (features, tokens) = dataset
+            .document_features::<usize>(0, &vocabulary)
+            .expect("failed to vectorize");
+        assert_eq!(tokens, ["free", "prize", "free", "cash"]);
+        let mut expected = vec![0; vocabulary.len()];
+        for token in tokens {
+            expected[vocabulary.get(token).unwrap()] += 1;
+        }
+        assert_eq!(features, expected);
+        assert_eq!(features[vocabulary.get("free").unwrap()], 2);
+
+        let (x, _) = RawDataset {
+            data: vec![RawData::new(Label::Spam, "free prize free cash")],
+        }
+        .tokenize()
+        .to_smartcore_with_vocabulary::<usize>(&vocabulary)
+        .unwrap();
+        assert_eq!(matrix_rows(&x)[0], features);
+
+        assert!(dataset.document_features::<usize>(2, &vocabulary).is_err());
+    }
+
+    #[test]
+    fn test_split_by() {
+        let dataset = RawDataset {
+            data: vec![
+                RawData::new(Label::Spam, "win a free prize now"),
+                RawData::new(Label::Ham, "ok"),
+                RawData::new(Label::Ham, "see you at lunch today"),
+                RawData::new(Label::Spam, "free cash"),
+                RawData::new(Label::Ham, "call me"),
+            ],
+        }
+        .tokenize();
+
+        let (long, short) = dataset.split_by(|data| data.tokens.len() > 3);
+        assert_eq!((long.len(), short.len()), (2, 3));
+        assert_eq!(long.labels, vec![Label::Spam, Label::Ham]);
+        assert_eq!(long.data[1].tokens[0], "see");
+        assert_eq!(short.labels, vec![Label::Ham, Label::Spam, Label::Ham]);
+        assert_eq!(short.data[1].tokens, vec!["free", "cash"]);
+    }
+
+    #[test]
+    fn test_drop_empty() {
+        let dataset = RawDataset {
+            data: vec![
+                RawData::new(Label::Spam, "Win a free prize!"),
+                RawData::new(Label::Ham, "You are there?"),
+                RawData::new(Label::Ham, "See you at lunch"),
+            ],
+        }
+        .tokenize_with(&Tokenizer::standard());
+        assert!(dataset.data[1].tokens.is_empty());
+
+        let dataset = dataset.drop_empty();
+        assert_eq!(dataset.labels, vec![Label::Spam, Label::Ham]);
+        assert_eq!(dataset.data[1].tokens, vec!["see", "lunch"]);
+    }
+
+    #[test]
+    fn test_map_tokens() {
+        let dataset = RawDataset {
+            data: vec![
+                RawData {
+                    subject: Some("win 1000 now".to_string()),
+                    ..RawData::new(Label::Spam, "call 0800 or txt win2 today")
+                },
+                RawData::new(Label::Ham, "123 456"),
+                RawData::new(Label::Ham, "see you at 6"),
+            ],
+        }
+        .tokenize()
+        .map_tokens(|token| {
+            if token.chars().any(|c| c.is_ascii_digit()) {
+                None
+            } else {
+                Some(token)
+            }
+        });
+
+        assert_eq!(dataset.labels, vec![Label::Spam, Label::Ham, Label::Ham]);
+        assert_eq!(dataset.data[0].subject_tokens(), ["win", "now"]);
+        assert_eq!(
+            dataset.data[0].body_tokens(),
+            ["call", "or", "txt", "today"]
+        );
+        assert!(dataset.data[1].tokens.is_empty());
+        assert_eq!(dataset.data[2].tokens, vec!["see", "you", "at"]);
+    }
 }
@@ -0,0 +1,135 @@
+//! Command line tool to probe the spam model without Fluvio.
+//!
+//! ```text
+//! sms-data-clean train [--dataset <path>] [--model <path>]
+//! sms-data-clean classify [--model <path>] [--top <n>] [message...]
+//! ```
+//!
+//! `train` trains the model the same way the smartmodules build it, on the labeled
+//! file passed with `--dataset`, `SMSSpamCollection` by default, and saves it to
+//! `--model`, `model.json` by default. `classify` loads that file instead of
+//! training again. Without a message on the command line, one is read from stdin.
+use std::io::Read;
+use std::process::ExitCode;
+
+use sms_data_clean::codegen::{load_classifier, train};
+use sms_data_clean::DEFAULT_TOP_TOKENS;
+
+const USAGE: &str = "\
+usage: sms-data-clean train [--dataset <path>] [--model <path>]
+       sms-data-clean classify [--model <path>] [--top <n>] [message...]";
+
+const DEFAULT_DATASET: &str = "SMSSpamCollection";
+
+const DEFAULT_MODEL: &str = "model.json";
+
+struct TrainArgs {
+    dataset: String,
+    model: String,
+}
+
+struct ClassifyArgs {
+    model: String,
+    top: usize,
+    message: Option<String>,
+}
+
+enum Command {
+    Train(TrainArgs),
+    Classify(ClassifyArgs),
+}
+
+fn parse_train_args<I: Iterator<Item = String>>(mut args: I) -> Result<TrainArgs, String> {
+    let mut dataset = DEFAULT_DATASET.to_string();
+    let mut model = DEFAULT_MODEL.to_string();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--dataset" => dataset = args.next().ok_or("--dataset expects a path")?,
+            "--model" => model = args.next().ok_or("--model expects a path")?,
+            _ => return Err(format!("unexpected argument `{arg}`\n{USAGE}")),
+        }
+    }
+    Ok(TrainArgs { dataset, model })
+}
+
+fn parse_classify_args<I: Iterator<Item = String>>(mut args: I) -> Result<ClassifyArgs, String> {
+    let mut model = DEFAULT_MODEL.to_string();
+    let mut top = DEFAULT_TOP_TOKENS;
+    let mut words = Vec::new();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--model" => model = args.next().ok_or("--model expects a path")?,
+            "--top" => {
+                top = args
+                    .next()
+                    .and_then(|top| top.parse().ok())
+                    .ok_or("--top expects a number of tokens")?
+            }
+            _ => words.push(arg),
+        }
+    }
+    Ok(ClassifyArgs {
+        model,
+        top,
+        message: if words.is_empty() {
+            None
+        } else {
+            Some(words.join(" "))
+        },
+    })
+}
+
+fn train_model(args: TrainArgs) -> Result<(), Box<dyn std::error::Error>> {
+    let trained = train(&args.dataset)?;
+    trained.save(&args.model)?;
+    println!("accuracy: {:.4}", trained.accuracy);
+    println!("saved to {}", args.model);
+    Ok(())
+}
+
+fn classify(args: ClassifyArgs) -> Result<(), Box<dyn std::error::Error>> {
+    let message = match args.message {
+        Some(message) => message,
+        None => {
+            let mut message = String::new();
+            std::io::stdin().read_to_string(&mut message)?;
+            message.trim().to_string()
+        }
+    };
+
+    let classifier = load_classifier(&args.model)
+        .map_err(|err| format!("failed to load the model `{}`: {err}", args.model))?;
+    let (prediction, contributions) = classifier.predict_explain(&message)?;
+
+    println!("verdict: {}", if prediction.spam { "spam" } else { "ham" });
+    println!("spam_probability: {:.4}", prediction.spam_probability);
+    println!("top tokens:");
+    for (token, contribution) in contributions.iter().take(args.top) {
+        println!("  {token}\t{contribution:.4}");
+    }
+    Ok(())
+}
+
+fn main() -> ExitCode {
+    let mut args = std::env::args().skip(1);
+    let command = match args.next().as_deref() {
+        Some("train") => parse_train_args(args).map(Command::Train),
+        Some("classify") => parse_classify_args(args).map(Command::Classify),
+        _ => Err(USAGE.to_string()),
+    };
+    let result = match command {
+        Ok(Command::Train(args)) => train_model(args),
+        Ok(Command::Classify(args)) => classify(args),
+        Err(err) => {
+            eprintln!("{err}");
+            return ExitCode::from(2);
+        }
+    };
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("error: {err}");
+            ExitCode::FAILURE
+        }
+    }
+}
@@ -0,0 +1,253 @@
+use std::collections::HashMap;
+use std::fmt;
+
+use crate::Label;
+
+/// Counts of every (true, predicted) label pair.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConfusionMatrix {
+    classes: Vec<Label>,
+    counts: HashMap<(Label, Label), usize>,
+}
+
+impl ConfusionMatrix {
+    /// Panics if `truth` and `predicted` have different lengths.
+    pub fn new(truth: &[Label], predicted: &[Label]) -> Self {
+        assert_eq!(
+            truth.len(),
+            predicted.len(),
+            "truth and predicted labels differ in length"
+        );
+        let mut counts = HashMap::new();
+        for pair in truth.iter().copied().zip(predicted.iter().copied()) {
+            *counts.entry(pair).or_insert(0) += 1;
+        }
+        let mut classes = truth.iter().chain(predicted).copied().collect::<Vec<_>>();
+        classes.sort();
+        classes.dedup();
+        Self { classes, counts }
+    }
+
+    /// Labels seen in either slice, in ascending order.
+    pub fn classes(&self) -> &[Label] {
+        &self.classes
+    }
+
+    pub fn count(&self, truth: Label, predicted: Label) -> usize {
+        self.counts.get(&(truth, predicted)).copied().unwrap_or(0)
+    }
+
+    pub fn total(&self) -> usize {
+        self.counts.values().sum()
+    }
+
+    pub fn true_positives(&self, class: Label) -> usize {
+        self.count(class, class)
+    }
+
+    pub fn false_positives(&self, class: Label) -> usize {
+        self.counts
+            .iter()
+            .filter(|((truth, predicted), _)| *truth != class && *predicted == class)
+            .map(|(_, count)| count)
+            .sum()
+    }
+
+    pub fn false_negatives(&self, class: Label) -> usize {
+        self.counts
+            .iter()
+            .filter(|((truth, predicted), _)| *truth == class && *predicted != class)
+            .map(|(_, count)| count)
+            .sum()
+    }
+
+    pub fn true_negatives(&self, class: Label) -> usize {
+        self.total()
+            - self.true_positives(class)
+            - self.false_positives(class)
+            - self.false_negatives(class)
+    }
+
+    pub fn accuracy(&self) -> f64 {
+        let correct = self
+            .classes
+            .iter()
+            .map(|class| self.true_positives(*class))
+            .sum::<usize>();
+        ratio(correct, self.total())
+    }
+
+    pub fn precision(&self, class: Label) -> f64 {
+        let tp = self.true_positives(class);
+        ratio(tp, tp + self.false_positives(class))
+    }
+
+    pub fn recall(&self, class: Label) -> f64 {
+        let tp = self.true_positives(class);
+        ratio(tp, tp + self.false_negatives(class))
+    }
+
+    pub fn f1(&self, class: Label) -> f64 {
+        let (precision, recall) = (self.precision(class), self.recall(class));
+        if precision + recall == 0.0 {
+            0.0
+        } else {
+            2.0 * precision * recall / (precision + recall)
+        }
+    }
+}
+
+/// Rates at which spam and ham messages score at or above `threshold`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RocPoint {
+    pub threshold: f64,
+    pub false_positive_rate: f64,
+    pub true_positive_rate: f64,
+}
+
+/// Receiver operating characteristic of a scored, labeled set, see [`roc_curve`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct RocCurve {
+    /// Area under the curve, the probability that a random spam message scores
+    /// higher than a random ham message, ties counting half.
+    pub auc: f64,
+    /// From `(0, 0)` at an infinite threshold to `(1, 1)` at the lowest score, one
+    /// point per distinct score.
+    pub points: Vec<RocPoint>,
+}
+
+/// ROC curve of spam `scores`, e.g. probabilities or log-odds, against the true
+/// `labels`. Unlike accuracy, it does not depend on a decision threshold nor on the
+/// share of spam in the set.
+///
+/// Without both classes the curve is degenerate and the AUC is zero.
+///
+/// Panics if `scores` and `labels` have different lengths.
+pub fn roc_curve(scores: &[f64], labels: &[bool]) -> RocCurve {
+    assert_eq!(
+        scores.len(),
+        labels.len(),
+        "scores and labels differ in length"
+    );
+    let positives = labels.iter().filter(|label| **label).count();
+    let negatives = labels.len() - positives;
+
+    let mut order = (0..scores.len()).collect::<Vec<_>>();
+    order.sort_by(|a, b| scores[*b].total_cmp(&scores[*a]));
+
+    let mut points = vec![RocPoint {
+        threshold: f64::INFINITY,
+        false_positive_rate: 0.0,
+        true_positive_rate: 0.0,
+    }];
+    let (mut true_positives, mut false_positives) = (0, 0);
+    for (position, index) in order.iter().enumerate() {
+        if labels[*index] {
+            true_positives += 1;
+        } else {
+            false_positives += 1;
+        }
+        let last_of_score = order
+            .get(position + 1)
+            .is_none_or(|next| scores[*next] != scores[*index]);
+        if last_of_score {
+            points.push(RocPoint {
+                threshold: scores[*index],
+                false_positive_rate: ratio(false_positives, negatives),
+                true_positive_rate: ratio(true_positives, positives),
+            });
+        }
+    }
+
+    let auc = points
+        .windows(2)
+        .map(|pair| {
+            let width = pair[1].false_positive_rate - pair[0].false_positive_rate;
+            width * (pair[0].true_positive_rate + pair[1].true_positive_rate) / 2.0
+        })
+        .sum();
+    RocCurve { auc, points }
+}
+
+/// `numerator / denominator`, or zero when there is nothing to divide.
+fn ratio(numerator: usize, denominator: usize) -> f64 {
+    if denominator == 0 {
+        0.0
+    } else {
+        numerator as f64 / denominator as f64
+    }
+}
+
+impl fmt::Display for ConfusionMatrix {
+    /// Rows are true labels, columns predicted labels.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:>12}", "true\\pred")?;
+        for class in &self.classes {
+            write!(f, "{:>8}", class)?;
+        }
+        writeln!(f)?;
+        for truth in &self.classes {
+            write!(f, "{:>12}", truth)?;
+            for predicted in &self.classes {
+                write!(f, "{:>8}", self.count(*truth, *predicted))?;
+            }
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::Label::{Ham, Spam};
+
+    use super::{roc_curve, ConfusionMatrix};
+
+    #[test]
+    fn test_confusion_matrix() {
+        let truth = [Spam, Spam, Spam, Ham, Ham, Ham, Ham, Ham];
+        let predicted = [Spam, Spam, Ham, Spam, Ham, Ham, Ham, Ham];
+        let matrix = ConfusionMatrix::new(&truth, &predicted);
+
+        assert_eq!(matrix.true_positives(Spam), 2);
+        assert_eq!(matrix.false_positives(Spam), 1);
+        assert_eq!(matrix.false_negatives(Spam), 1);
+        assert_eq!(matrix.true_negatives(Spam), 4);
+        assert_eq!(matrix.true_positives(Ham), 4);
+        assert!((matrix.accuracy() - 0.75).abs() < 1e-12);
+        assert!((matrix.precision(Spam) - 2.0 / 3.0).abs() < 1e-12);
+        assert!((matrix.recall(Spam) - 2.0 / 3.0).abs() < 1e-12);
+        assert!((matrix.f1(Spam) - 2.0 / 3.0).abs() < 1e-12);
+        assert!((matrix.precision(Ham) - 0.8).abs() < 1e-12);
+        assert!((matrix.recall(Ham) - 0.8).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_roc_curve() {
+        let roc = roc_curve(&[0.1, 0.4, 0.35, 0.8], &[false, false, true, true]);
+        assert!((roc.auc - 0.75).abs() < 1e-12);
+        let rates = roc
+            .points
+            .iter()
+            .map(|point| (point.false_positive_rate, point.true_positive_rate))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            rates,
+            vec![(0.0, 0.0), (0.0, 0.5), (0.5, 0.5), (0.5, 1.0), (1.0, 1.0)]
+        );
+        assert_eq!(roc.points[1].threshold, 0.8);
+
+        let tied = roc_curve(&[0.5, 0.5], &[true, false]);
+        assert_eq!(tied.points.len(), 2);
+        assert!((tied.auc - 0.5).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_display() {
+        let matrix = ConfusionMatrix::new(&[Ham, Spam, Spam], &[Ham, Ham, Spam]);
+        assert_eq!(
+            matrix.to_string(),
+            "   true\\pred     ham    spam\n         ham       1       0\n        spam       1       1\n"
+        );
+    }
+}
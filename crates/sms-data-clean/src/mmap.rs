@@ -0,0 +1,46 @@
+use std::fs::File;
+use std::path::Path;
+
+use memmap2::Mmap;
+
+use crate::{decode, parse_record, Label, RawDataset};
+
+impl RawDataset {
+    /// Like [`RawDataset::from_file`], but parses the lines straight from a memory
+    /// mapping of the file instead of reading it into memory first.
+    ///
+    /// Only the messages themselves are copied out of the mapping, unless the file
+    /// is not valid UTF-8 and has to be decoded as Latin-1. The file must not be
+    /// modified while it is being loaded.
+    pub fn from_file_mmap<P>(path: P) -> Result<Self, std::io::Error>
+    where
+        P: AsRef<Path>,
+    {
+        let file = File::open(path)?;
+        // SAFETY: the mapping is read-only and dropped before returning, callers are
+        // told not to modify the file meanwhile.
+        let mmap = unsafe { Mmap::map(&file)? };
+        let data = decode(&mmap)
+            .lines()
+            .map(|line| parse_record(line, |label| label.parse::<Label>().ok()))
+            .collect::<Result<Vec<_>, std::io::Error>>()?;
+        Ok(Self { data })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::RawDataset;
+
+    #[test]
+    fn test_mmap_matches_from_file() {
+        let mapped = RawDataset::from_file_mmap("../../SMSSpamCollection").expect("failed to map");
+        let read = RawDataset::from_file("../../SMSSpamCollection").expect("failed to read");
+
+        assert_eq!(mapped.len(), read.len());
+        for (mapped, read) in mapped.data.iter().zip(&read.data) {
+            assert_eq!(mapped.label, read.label);
+            assert_eq!(mapped.sms, read.sms);
+        }
+    }
+}
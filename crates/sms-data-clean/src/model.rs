@@ -0,0 +1,437 @@
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+use smartcore::linalg::basic::arrays::Array;
+use smartcore::linalg::basic::matrix::DenseMatrix;
+use smartcore::naive_bayes::multinomial::MultinomialNB;
+use smartcore::numbers::basenum::Number;
+
+use crate::{Dataset, LabelEncoder, Vocabulary};
+
+/// Naive Bayes model trained on word counts, as embedded in the smartmodule.
+pub type NaiveBayesModel = MultinomialNB<usize, usize, DenseMatrix<usize>, Vec<usize>>;
+
+/// Retrains a model on `dataset` after appending its unseen tokens to `vocabulary`.
+///
+/// Tokens already present keep their index, so features of the previous model
+/// still line up with the columns of the new one.
+pub fn retrain_with_extended_vocabulary(
+    mut vocabulary: Vocabulary,
+    dataset: Dataset,
+) -> Result<(NaiveBayesModel, Vocabulary), std::io::Error> {
+    vocabulary.extend(&dataset);
+    let (x, y) = dataset.to_smartcore_with_vocabulary::<usize>(&vocabulary)?;
+    let model = MultinomialNB::fit(&x, &y, Default::default())
+        .map_err(|err| std::io::Error::new(std::io::ErrorKind::Other, err.to_string()))?;
+    Ok((model, vocabulary))
+}
+
+/// Joint log-likelihood `ln P(class) + sum(x_j * ln P(token_j | class))` of every class,
+/// in the order of `model.classes()`.
+pub fn class_log_likelihoods(model: &NaiveBayesModel, features: &[usize]) -> Vec<f64> {
+    let total = model.class_count().iter().sum::<usize>() as f64;
+    model
+        .class_count()
+        .iter()
+        .zip(model.feature_log_prob())
+        .map(|(count, log_probs)| {
+            let evidence = features
+                .iter()
+                .zip(log_probs)
+                .map(|(x, log_prob)| *x as f64 * log_prob)
+                .sum::<f64>();
+            (*count as f64 / total).ln() + evidence
+        })
+        .collect()
+}
+
+/// `ln P(spam | x) - ln P(ham | x)`, the raw score used for calibration.
+pub fn spam_log_odds(model: &NaiveBayesModel, features: &[usize]) -> f64 {
+    let likelihoods = class_log_likelihoods(model, features);
+    let likelihood = |class: usize| {
+        model
+            .classes()
+            .iter()
+            .position(|label| *label == class)
+            .map(|index| likelihoods[index])
+            .unwrap_or(f64::NEG_INFINITY)
+    };
+    likelihood(1) - likelihood(0)
+}
+
+/// [`spam_log_odds`] of a model folded into one weight per feature, computed once so
+/// that scoring a message is a dot product over its non-zero counts.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LogOddsWeights {
+    /// `ln P(spam) - ln P(ham)`
+    pub bias: f64,
+    /// `ln P(token | spam) - ln P(token | ham)` of every feature column.
+    pub weights: Vec<f64>,
+}
+
+impl LogOddsWeights {
+    pub fn new(model: &NaiveBayesModel) -> Self {
+        let class_index = |class: usize| model.classes().iter().position(|label| *label == class);
+        let total = model.class_count().iter().sum::<usize>() as f64;
+        let log_prior = |index: usize| (model.class_count()[index] as f64 / total).ln();
+        let log_probs = model.feature_log_prob();
+        match (class_index(1), class_index(0)) {
+            (Some(spam), Some(ham)) => Self {
+                bias: log_prior(spam) - log_prior(ham),
+                weights: log_probs[spam]
+                    .iter()
+                    .zip(&log_probs[ham])
+                    .map(|(spam, ham)| spam - ham)
+                    .collect(),
+            },
+            // Like `spam_log_odds`, a model trained on a single class always
+            // predicts it.
+            (spam, _) => Self {
+                bias: if spam.is_some() {
+                    f64::INFINITY
+                } else {
+                    f64::NEG_INFINITY
+                },
+                weights: vec![0.0; model.n_features()],
+            },
+        }
+    }
+
+    /// Same as [`spam_log_odds`] up to rounding.
+    pub fn log_odds(&self, features: &[usize]) -> f64 {
+        self.bias
+            + features
+                .iter()
+                .zip(&self.weights)
+                .filter(|(x, _)| **x != 0)
+                .map(|(x, weight)| *x as f64 * weight)
+                .sum::<f64>()
+    }
+}
+
+/// Per-token log-probabilities of a trained model, for auditing.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FeatureStats {
+    pub token: String,
+    /// `ln P(token | ham)`
+    pub log_prob_ham: f64,
+    /// `ln P(token | spam)`
+    pub log_prob_spam: f64,
+}
+
+/// Joins the feature log-probability table of `model` with the token names of
+/// `vocabulary`, one row per token in column order.
+///
+/// A class that was absent from the training data gets `-inf` for every token.
+pub fn feature_stats(model: &NaiveBayesModel, vocabulary: &Vocabulary) -> Vec<FeatureStats> {
+    let log_probs = |class: usize| {
+        model
+            .classes()
+            .iter()
+            .position(|label| *label == class)
+            .map(|index| &model.feature_log_prob()[index])
+    };
+    let (ham, spam) = (log_probs(0), log_probs(1));
+    let log_prob = |log_probs: Option<&Vec<f64>>, index: usize| {
+        log_probs.map_or(f64::NEG_INFINITY, |log_probs| log_probs[index])
+    };
+
+    let mut tokens = vocabulary.iter().collect::<Vec<_>>();
+    tokens.sort_by_key(|(_, index)| *index);
+    tokens
+        .into_iter()
+        .map(|(token, index)| FeatureStats {
+            token: token.to_string(),
+            log_prob_ham: log_prob(ham, index),
+            log_prob_spam: log_prob(spam, index),
+        })
+        .collect()
+}
+
+/// Number of tokens a top-token listing usually asks for.
+pub const DEFAULT_TOP_TOKENS: usize = 20;
+
+/// The `k` tokens with the largest `ln P(token | spam) - ln P(token | ham)`, most
+/// spam-indicative first, with that difference.
+pub fn top_spam_tokens(
+    model: &NaiveBayesModel,
+    vocabulary: &Vocabulary,
+    k: usize,
+) -> Vec<(String, f64)> {
+    top_tokens(model, vocabulary, k, |a, b| b.total_cmp(a))
+}
+
+/// Like [`top_spam_tokens`] from the other end: the `k` tokens with the most negative
+/// difference, most ham-indicative first.
+pub fn top_ham_tokens(
+    model: &NaiveBayesModel,
+    vocabulary: &Vocabulary,
+    k: usize,
+) -> Vec<(String, f64)> {
+    top_tokens(model, vocabulary, k, |a, b| a.total_cmp(b))
+}
+
+fn top_tokens<F>(
+    model: &NaiveBayesModel,
+    vocabulary: &Vocabulary,
+    k: usize,
+    order: F,
+) -> Vec<(String, f64)>
+where
+    F: Fn(&f64, &f64) -> std::cmp::Ordering,
+{
+    let mut tokens = feature_stats(model, vocabulary)
+        .into_iter()
+        .map(|row| (row.token, row.log_prob_spam - row.log_prob_ham))
+        .collect::<Vec<_>>();
+    tokens.sort_by(|a, b| order(&a.1, &b.1).then_with(|| a.0.cmp(&b.0)));
+    tokens.truncate(k);
+    tokens
+}
+
+/// Parameters of a trained model in a plain JSON schema, to serve it from runtimes
+/// other than Rust.
+///
+/// A message scores `class_log_prior[c] + sum_j x_j * feature_log_prob[c][j]` for
+/// every class `c`, where `x_j` counts the occurrences of `vocabulary[j]` among its
+/// tokens, and the highest scoring class wins. The tokens must be produced the way
+/// the model was trained, for the bundled model with
+/// [`Tokenizer::standard`](crate::Tokenizer::standard):
+/// lowercasing, deleting ASCII punctuation, splitting on whitespace and dropping
+/// the NLTK English stop words.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PortableModel {
+    /// Names of the classes, `ham` or `spam`, in the order of the rows below.
+    pub classes: Vec<String>,
+    pub class_log_prior: Vec<f64>,
+    /// One row per class, one column per token of `vocabulary`.
+    pub feature_log_prob: Vec<Vec<f64>>,
+    /// Token of every feature column.
+    pub vocabulary: Vec<String>,
+}
+
+impl PortableModel {
+    pub fn new(model: &NaiveBayesModel, vocabulary: &Vocabulary) -> Self {
+        let encoder = LabelEncoder::default();
+        let total = model.class_count().iter().sum::<usize>() as f64;
+        let mut tokens = vec![String::new(); vocabulary.len()];
+        for (token, index) in vocabulary.iter() {
+            tokens[index] = token.to_string();
+        }
+        Self {
+            classes: model
+                .classes()
+                .iter()
+                .map(|class| match encoder.decode(*class) {
+                    Some(label) => label.to_string(),
+                    None => class.to_string(),
+                })
+                .collect(),
+            class_log_prior: model
+                .class_count()
+                .iter()
+                .map(|count| (*count as f64 / total).ln())
+                .collect(),
+            feature_log_prob: model.feature_log_prob().clone(),
+            vocabulary: tokens,
+        }
+    }
+
+    /// Writes the parameters as JSON.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), std::io::Error> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        serde_json::to_writer(&mut writer, self)?;
+        writer.flush()
+    }
+}
+
+/// Copies the rows of a feature matrix out into plain vectors.
+pub fn matrix_rows<T: Number>(x: &DenseMatrix<T>) -> Vec<Vec<T>> {
+    let (rows, cols) = x.shape();
+    (0..rows)
+        .map(|row| (0..cols).map(|col| *x.get((row, col))).collect())
+        .collect()
+}
+
+/// Fraction of nonzero entries of a feature matrix, zero for an empty one. Bag of
+/// words rows rarely use more than a handful of the vocabulary, so a low value
+/// means a sparse representation would save most of the memory.
+pub fn matrix_sparsity<T: Number>(x: &DenseMatrix<T>) -> f64 {
+    let (rows, cols) = x.shape();
+    if rows * cols == 0 {
+        return 0.0;
+    }
+    let nonzero = (0..rows)
+        .flat_map(|row| (0..cols).map(move |col| (row, col)))
+        .filter(|position| *x.get(*position) != T::zero())
+        .count();
+    nonzero as f64 / (rows * cols) as f64
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{fixtures, Label, RawData, RawDataset, Vocabulary};
+
+    use smartcore::linalg::basic::matrix::DenseMatrix;
+
+    use super::{
+        feature_stats, matrix_sparsity, retrain_with_extended_vocabulary, spam_log_odds,
+        top_ham_tokens, top_spam_tokens, LogOddsWeights, PortableModel, DEFAULT_TOP_TOKENS,
+    };
+
+    #[test]
+    fn test_retrain_with_extended_vocabulary() {
+        let old = RawDataset {
+            data: vec![
+                RawData::new(Label::Ham, "see you at lunch"),
+                RawData::new(Label::Spam, "win a free prize"),
+            ],
+        }
+        .tokenize();
+        let vocabulary = Vocabulary::from_dataset(&old);
+        let lunch = vocabulary.get("lunch");
+        let free = vocabulary.get("free");
+
+        let new = RawDataset {
+            data: vec![
+                RawData::new(Label::Ham, "lunch tomorrow"),
+                RawData::new(Label::Spam, "free crypto giveaway"),
+            ],
+        }
+        .tokenize();
+        let (model, vocabulary) =
+            retrain_with_extended_vocabulary(vocabulary, new).expect("failed to retrain");
+
+        assert_eq!(vocabulary.get("lunch"), lunch);
+        assert_eq!(vocabulary.get("free"), free);
+        assert_eq!(vocabulary.get("tomorrow"), Some(8));
+        assert_eq!(model.n_features(), vocabulary.len());
+    }
+
+    #[test]
+    fn test_matrix_sparsity() {
+        let x = DenseMatrix::from_2d_vec(&vec![vec![0usize, 2, 0, 0], vec![1, 0, 0, 3]]);
+        assert_eq!(matrix_sparsity(&x), 3.0 / 8.0);
+
+        let (x, _, vocabulary) =
+            crate::create_smartcore_input::<usize, _>("../../SMSSpamCollection")
+                .expect("failed to load");
+        let sparsity = matrix_sparsity(&x);
+        // A message uses about a dozen of the thousands of tokens of the vocabulary.
+        assert!(vocabulary.len() > 1000);
+        assert!(sparsity > 0.0 && sparsity < 0.005, "{}", sparsity);
+    }
+
+    #[test]
+    fn test_feature_stats() {
+        let classifier = fixtures::classifier();
+        let stats = feature_stats(classifier.model(), classifier.vocabulary());
+
+        assert_eq!(stats.len(), classifier.vocabulary().len());
+        for (index, row) in stats.iter().enumerate() {
+            assert_eq!(classifier.vocabulary().get(&row.token), Some(index));
+            assert!(row.log_prob_ham < 0.0 && row.log_prob_spam < 0.0);
+        }
+        let prize = stats.iter().find(|row| row.token == "prize").unwrap();
+        assert!(prize.log_prob_spam > prize.log_prob_ham);
+    }
+
+    #[test]
+    fn test_top_tokens() {
+        let classifier = fixtures::classifier();
+        let (model, vocabulary) = (classifier.model(), classifier.vocabulary());
+
+        let spam = top_spam_tokens(model, vocabulary, 3);
+        assert_eq!(spam.len(), 3);
+        assert!(spam.windows(2).all(|pair| pair[0].1 >= pair[1].1));
+        let ham = top_ham_tokens(model, vocabulary, 3);
+        assert_eq!(ham.len(), 3);
+        assert!(ham.windows(2).all(|pair| pair[0].1 <= pair[1].1));
+        assert!(spam[0].1 > 0.0 && ham[0].1 < 0.0);
+
+        let all = top_spam_tokens(model, vocabulary, DEFAULT_TOP_TOKENS.max(vocabulary.len()));
+        assert_eq!(all.len(), vocabulary.len());
+        assert_eq!(all[..3], spam[..]);
+        assert!(top_spam_tokens(model, vocabulary, 0).is_empty());
+    }
+
+    #[test]
+    fn test_log_odds_weights() {
+        let classifier = fixtures::classifier();
+        let weights = LogOddsWeights::new(classifier.model());
+        assert_eq!(weights.weights.len(), classifier.vocabulary().len());
+
+        for text in [
+            "claim your free prize",
+            "see you at lunch",
+            "",
+            "free lunch",
+        ] {
+            let x = classifier.features(text);
+            let expected = spam_log_odds(classifier.model(), &x);
+            assert!((weights.log_odds(&x) - expected).abs() < 1e-9, "{}", text);
+        }
+    }
+
+    #[test]
+    fn test_portable_model() {
+        let classifier = fixtures::classifier();
+        let path = std::env::temp_dir().join("sms-data-clean-portable-model.json");
+        PortableModel::new(classifier.model(), classifier.vocabulary())
+            .save(&path)
+            .expect("failed to save");
+
+        // Score with nothing but the JSON, as another runtime would.
+        let json: serde_json::Value =
+            serde_json::from_slice(&std::fs::read(&path).expect("failed to read")).unwrap();
+        let floats = |value: &serde_json::Value| {
+            value
+                .as_array()
+                .unwrap()
+                .iter()
+                .map(|x| x.as_f64().unwrap())
+                .collect::<Vec<_>>()
+        };
+        let vocabulary = json["vocabulary"].as_array().unwrap();
+        let priors = floats(&json["class_log_prior"]);
+        let log_probs = json["feature_log_prob"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(floats)
+            .collect::<Vec<_>>();
+        let predict = |tokens: &[String]| {
+            let scores = priors
+                .iter()
+                .zip(&log_probs)
+                .map(|(prior, log_probs)| {
+                    prior
+                        + tokens
+                            .iter()
+                            .filter_map(|token| vocabulary.iter().position(|t| t == token.as_str()))
+                            .map(|column| log_probs[column])
+                            .sum::<f64>()
+                })
+                .collect::<Vec<_>>();
+            let best = (0..scores.len())
+                .max_by(|a, b| scores[*a].total_cmp(&scores[*b]))
+                .unwrap();
+            json["classes"][best].as_str().unwrap().to_string()
+        };
+
+        for text in [
+            "claim your free prize",
+            "see you at lunch",
+            "free lunch",
+            "call me later",
+            "win cash",
+        ] {
+            let x = DenseMatrix::from_2d_vec(&vec![classifier.features(text)]);
+            let expected = classifier.model().predict(&x).unwrap()[0];
+            let expected = if expected == 1 { "spam" } else { "ham" };
+            assert_eq!(predict(&classifier.tokenize(text)), expected, "{}", text);
+        }
+    }
+}
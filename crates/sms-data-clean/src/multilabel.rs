@@ -0,0 +1,125 @@
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use std::path::Path;
+use std::str::FromStr;
+
+use crate::{Label, RawData, RawDataset};
+
+/// How the labels of a multi-label record are reduced to the single training label.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LabelPolicy {
+    /// The first recognised label of the record.
+    First,
+    /// The most severe label of the record, [`Label::Spam`] over [`Label::Ham`].
+    MostSevere,
+}
+
+impl LabelPolicy {
+    /// Returns `None` for a record without labels.
+    pub fn collapse(&self, labels: &[Label]) -> Option<Label> {
+        match self {
+            Self::First => labels.first().copied(),
+            Self::MostSevere => labels.iter().max().copied(),
+        }
+    }
+}
+
+/// A record with every label it was annotated with.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MultiLabelData {
+    pub labels: Vec<Label>,
+    pub sms: String,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct MultiLabelDataset {
+    pub data: Vec<MultiLabelData>,
+}
+
+impl MultiLabelDataset {
+    pub fn from_file<P>(path: P, delimiter: char) -> Result<Self, std::io::Error>
+    where
+        P: AsRef<Path>,
+    {
+        Self::from_reader(BufReader::new(File::open(path)?), delimiter)
+    }
+
+    /// Loads `labels<TAB>sms` lines whose label column lists several labels separated
+    /// by `delimiter`, e.g. `spam,promo`.
+    ///
+    /// Labels other than `ham` and `spam` are skipped, a record must have at least one
+    /// of those.
+    pub fn from_reader<R: BufRead>(reader: R, delimiter: char) -> Result<Self, std::io::Error> {
+        let data = reader
+            .lines()
+            .map(|line| {
+                let line = line?;
+                let (labels, sms) = line.split_once('\t').ok_or_else(|| {
+                    std::io::Error::new(std::io::ErrorKind::Other, "Missing delimeter")
+                })?;
+                let labels = labels
+                    .split(delimiter)
+                    .filter_map(|label| Label::from_str(label.trim()).ok())
+                    .collect::<Vec<_>>();
+                if labels.is_empty() {
+                    return Err(std::io::Error::new(
+                        std::io::ErrorKind::Other,
+                        "Invalid label",
+                    ));
+                }
+                Ok(MultiLabelData {
+                    labels,
+                    sms: sms.to_string(),
+                })
+            })
+            .collect::<Result<Vec<_>, std::io::Error>>()?;
+        Ok(Self { data })
+    }
+
+    /// Reduces every record to a single label for the binary model.
+    pub fn collapse(self, policy: LabelPolicy) -> RawDataset {
+        RawDataset {
+            data: self
+                .data
+                .into_iter()
+                .filter_map(|data| {
+                    let label = policy.collapse(&data.labels)?;
+                    Some(RawData::new(label, data.sms))
+                })
+                .collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::io::Cursor;
+
+    use crate::Label;
+
+    use super::{LabelPolicy, MultiLabelDataset};
+
+    #[test]
+    fn test_most_severe() {
+        let dataset = MultiLabelDataset::from_reader(
+            Cursor::new("ham,spam,promo\tWin a free prize\nham\tSee you later\n"),
+            ',',
+        )
+        .expect("failed to load");
+        assert_eq!(dataset.data[0].labels, vec![Label::Ham, Label::Spam]);
+
+        let first = dataset.clone().collapse(LabelPolicy::First);
+        assert_eq!(first.data[0].label, Label::Ham);
+
+        let collapsed = dataset.collapse(LabelPolicy::MostSevere);
+        assert_eq!(collapsed.data[0].label, Label::Spam);
+        assert_eq!(collapsed.data[0].sms, "Win a free prize");
+        assert_eq!(collapsed.data[1].label, Label::Ham);
+    }
+
+    #[test]
+    fn test_record_without_known_labels() {
+        let result = MultiLabelDataset::from_reader(Cursor::new("promo\tHello\n"), ',');
+        assert!(result.is_err());
+    }
+}
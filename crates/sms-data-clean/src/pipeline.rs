@@ -0,0 +1,369 @@
+use std::collections::{BTreeSet, HashSet};
+
+use stopwords::{Language, Stopwords, NLTK};
+
+use crate::{Dataset, NumberFormat, RawData, RawDataset, TokenizedData, UnicodeForm};
+
+/// A transformation of raw records, applied before tokenization.
+pub trait PreprocessStage {
+    fn apply(&self, data: RawData) -> RawData;
+}
+
+/// A transformation of tokenized records.
+pub trait TokenStage {
+    fn apply(&self, data: TokenizedData) -> TokenizedData;
+}
+
+/// Lowercases the text, see [`RawData::lowercase`].
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Lowercase;
+
+impl PreprocessStage for Lowercase {
+    fn apply(&self, data: RawData) -> RawData {
+        data.lowercase()
+    }
+}
+
+/// Case folds the text, see [`RawData::casefold`].
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Casefold {
+    pub turkic: bool,
+}
+
+impl PreprocessStage for Casefold {
+    fn apply(&self, data: RawData) -> RawData {
+        data.casefold(self.turkic)
+    }
+}
+
+/// Deletes ASCII punctuation, see [`RawData::without_punctuaction`].
+#[derive(Debug, Default, Clone, Copy)]
+pub struct WithoutPunctuation;
+
+impl PreprocessStage for WithoutPunctuation {
+    fn apply(&self, data: RawData) -> RawData {
+        data.without_punctuaction()
+    }
+}
+
+/// Splits punctuation into separate tokens, see [`RawData::separate_punctuation`].
+#[derive(Debug, Default, Clone, Copy)]
+pub struct SeparatePunctuation {
+    pub multi_exclaim: bool,
+}
+
+impl PreprocessStage for SeparatePunctuation {
+    fn apply(&self, data: RawData) -> RawData {
+        data.separate_punctuation(self.multi_exclaim)
+    }
+}
+
+/// Shortens character runs, see [`RawData::normalize_repeats`].
+#[derive(Debug, Clone, Copy)]
+pub struct NormalizeRepeats(pub usize);
+
+impl PreprocessStage for NormalizeRepeats {
+    fn apply(&self, data: RawData) -> RawData {
+        data.normalize_repeats(self.0)
+    }
+}
+
+/// Replaces numbers with sentinel tokens, see [`RawData::normalize_numbers`].
+#[derive(Debug, Default, Clone, Copy)]
+pub struct NormalizeNumbers(pub NumberFormat);
+
+impl PreprocessStage for NormalizeNumbers {
+    fn apply(&self, data: RawData) -> RawData {
+        data.normalize_numbers(self.0)
+    }
+}
+
+/// Canonicalizes code points, see [`RawData::normalize_unicode`].
+#[derive(Debug, Default, Clone, Copy)]
+pub struct NormalizeUnicode(pub UnicodeForm);
+
+impl PreprocessStage for NormalizeUnicode {
+    fn apply(&self, data: RawData) -> RawData {
+        data.normalize_unicode(self.0)
+    }
+}
+
+/// Bounds the message length, see [`RawData::truncate_chars`].
+#[derive(Debug, Clone, Copy)]
+pub struct TruncateChars(pub usize);
+
+impl PreprocessStage for TruncateChars {
+    fn apply(&self, data: RawData) -> RawData {
+        data.truncate_chars(self.0)
+    }
+}
+
+/// Removes the NLTK English stopwords.
+#[derive(Debug, Clone)]
+pub struct StopWords {
+    stops: HashSet<&'static str>,
+}
+
+impl Default for StopWords {
+    fn default() -> Self {
+        Self {
+            stops: NLTK::stopwords(Language::English)
+                .unwrap()
+                .iter()
+                .copied()
+                .collect(),
+        }
+    }
+}
+
+impl StopWords {
+    pub fn contains(&self, token: &str) -> bool {
+        self.stops.contains(token)
+    }
+
+    /// Like [`TokenStage::apply`], also returning the distinct tokens it removed.
+    pub fn apply_with_removed(&self, data: TokenizedData) -> (TokenizedData, BTreeSet<String>) {
+        let mut removed = BTreeSet::new();
+        let data = data.retain(|token| {
+            let stop = self.contains(token);
+            if stop && !removed.contains(token) {
+                removed.insert(token.to_string());
+            }
+            !stop
+        });
+        (data, removed)
+    }
+}
+
+impl TokenStage for StopWords {
+    fn apply(&self, data: TokenizedData) -> TokenizedData {
+        data.retain(|token| !self.contains(token))
+    }
+}
+
+/// Drops tokens shorter than the given number of characters.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct MinTokenLen(pub usize);
+
+impl TokenStage for MinTokenLen {
+    fn apply(&self, data: TokenizedData) -> TokenizedData {
+        data.retain(|token| token.chars().count() >= self.0)
+    }
+}
+
+/// Keeps at most the given number of leading tokens.
+#[derive(Debug, Clone, Copy)]
+pub struct TruncateTokens(pub usize);
+
+impl TokenStage for TruncateTokens {
+    fn apply(&self, mut data: TokenizedData) -> TokenizedData {
+        data.tokens.truncate(self.0);
+        data.subject_len = data.subject_len.min(self.0);
+        data
+    }
+}
+
+/// Splits tokens where letters meet digits, e.g. "call2win" into "call", "2" and
+/// "win", since the parts are more likely to be in the vocabulary than the whole.
+///
+/// Other characters, like currency symbols, form parts of their own, so "£1000"
+/// becomes "£" and "1000". With `keep_original`, the unsplit token is kept in front
+/// of its parts.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct SplitDigits {
+    pub keep_original: bool,
+}
+
+impl TokenStage for SplitDigits {
+    fn apply(&self, data: TokenizedData) -> TokenizedData {
+        #[derive(PartialEq)]
+        enum Class {
+            Letter,
+            Digit,
+            Other,
+        }
+        let class = |c: char| {
+            if c.is_numeric() {
+                Class::Digit
+            } else if c.is_alphabetic() {
+                Class::Letter
+            } else {
+                Class::Other
+            }
+        };
+
+        let mut tokens = Vec::with_capacity(data.tokens.len());
+        let mut subject_len = 0;
+        for (position, token) in data.tokens.into_iter().enumerate() {
+            let mut parts = Vec::new();
+            let mut start = 0;
+            let mut previous = None;
+            for (index, c) in token.char_indices() {
+                let current = class(c);
+                if previous
+                    .as_ref()
+                    .is_some_and(|previous| *previous != current)
+                {
+                    parts.push(token[start..index].to_string());
+                    start = index;
+                }
+                previous = Some(current);
+            }
+            if parts.is_empty() {
+                tokens.push(token);
+            } else {
+                parts.push(token[start..].to_string());
+                if self.keep_original {
+                    tokens.push(token);
+                }
+                tokens.extend(parts);
+            }
+            if position < data.subject_len {
+                subject_len = tokens.len();
+            }
+        }
+        TokenizedData {
+            tokens,
+            subject_len,
+            original: data.original,
+        }
+    }
+}
+
+/// Appends every pair of tokens at most `max_skip` tokens apart as a `first_second`
+/// token, e.g. "call me now" gains "call_me", "call_now" and "me_now" with a
+/// `max_skip` of one, capturing phrases with a filler word in between.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct SkipBigrams {
+    pub max_skip: usize,
+}
+
+impl TokenStage for SkipBigrams {
+    fn apply(&self, mut data: TokenizedData) -> TokenizedData {
+        let n = data.tokens.len();
+        for first in 0..n {
+            for second in (first + 1)..n.min(first + self.max_skip + 2) {
+                let bigram = format!("{}_{}", data.tokens[first], data.tokens[second]);
+                data.tokens.push(bigram);
+            }
+        }
+        data
+    }
+}
+
+/// Ordered list of stages turning a [`RawDataset`] into a [`Dataset`].
+#[derive(Default)]
+pub struct Pipeline {
+    stages: Vec<Box<dyn PreprocessStage>>,
+    token_stages: Vec<Box<dyn TokenStage>>,
+}
+
+impl Pipeline {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The preprocessing used to train the bundled model.
+    pub fn standard() -> Self {
+        Self::new()
+            .with_stage(Lowercase)
+            .with_stage(WithoutPunctuation)
+            .with_token_stage(StopWords::default())
+    }
+
+    /// Appends a stage run, in registration order, before tokenization.
+    pub fn with_stage<S: PreprocessStage + 'static>(mut self, stage: S) -> Self {
+        self.stages.push(Box::new(stage));
+        self
+    }
+
+    /// Appends a stage run, in registration order, after tokenization.
+    pub fn with_token_stage<S: TokenStage + 'static>(mut self, stage: S) -> Self {
+        self.token_stages.push(Box::new(stage));
+        self
+    }
+
+    pub fn run(&self, dataset: RawDataset) -> Dataset {
+        let dataset = self
+            .stages
+            .iter()
+            .fold(dataset, |dataset, stage| dataset.apply(stage.as_ref()));
+        self.token_stages
+            .iter()
+            .fold(dataset.tokenize(), |dataset, stage| {
+                dataset.apply(stage.as_ref())
+            })
+    }
+}
+
+impl RawDataset {
+    pub fn apply<S: PreprocessStage + ?Sized>(self, stage: &S) -> Self {
+        Self {
+            data: self.data.into_iter().map(|row| stage.apply(row)).collect(),
+        }
+    }
+}
+
+impl Dataset {
+    pub fn apply<S: TokenStage + ?Sized>(self, stage: &S) -> Self {
+        Self {
+            labels: self.labels,
+            data: self.data.into_iter().map(|row| stage.apply(row)).collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{Label, RawData, RawDataset};
+
+    use super::{Lowercase, Pipeline, PreprocessStage, StopWords, WithoutPunctuation};
+
+    struct Shout(&'static str);
+
+    impl PreprocessStage for Shout {
+        fn apply(&self, data: RawData) -> RawData {
+            RawData {
+                sms: data.sms.replace(self.0, &self.0.to_uppercase()),
+                ..data
+            }
+        }
+    }
+
+    #[test]
+    fn test_stop_words_with_removed() {
+        let dataset = RawDataset {
+            data: vec![
+                RawData::new(Label::Ham, "I will call you when I get home"),
+                RawData::new(Label::Spam, "WIN cash now"),
+            ],
+        };
+        let (dataset, removed) = dataset.lowercase().tokenize().stop_words_with_removed();
+
+        assert_eq!(dataset.data[0].tokens, vec!["call", "get", "home"]);
+        assert!(removed[0].contains("you"));
+        assert!(removed[0].contains("i"));
+        assert!(!removed[0].contains("call"));
+        assert_eq!(dataset.data[1].tokens, vec!["win", "cash"]);
+        assert_eq!(removed[1].iter().collect::<Vec<_>>(), vec!["now"]);
+
+        let (data, _) = StopWords::default().apply_with_removed(dataset.data[1].clone());
+        assert_eq!(data.tokens, dataset.data[1].tokens);
+    }
+
+    #[test]
+    fn test_custom_stage() {
+        let dataset = RawDataset {
+            data: vec![RawData::new(Label::Spam, "This is URGENT, reply urgent!")],
+        };
+        let dataset = Pipeline::new()
+            .with_stage(Lowercase)
+            .with_stage(WithoutPunctuation)
+            .with_stage(Shout("urgent"))
+            .run(dataset);
+        assert_eq!(
+            dataset.data[0].tokens,
+            vec!["this", "is", "URGENT", "reply", "URGENT"]
+        );
+    }
+}
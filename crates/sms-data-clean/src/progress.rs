@@ -0,0 +1,143 @@
+use std::path::Path;
+
+use smartcore::linalg::basic::matrix::DenseMatrix;
+use smartcore::numbers::basenum::Number;
+
+use crate::{smartcore_input, Vocabulary};
+
+/// Step of [`create_smartcore_input_with_progress`] a [`ProcessedCount`] refers to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Phase {
+    Loading,
+    Tokenizing,
+    Vectorizing,
+}
+
+/// Number of records a phase has processed so far.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ProcessedCount {
+    pub phase: Phase,
+    pub processed: usize,
+    /// Whether this is the last report of the phase.
+    pub done: bool,
+}
+
+/// Like [`create_smartcore_input`](crate::create_smartcore_input), calling `progress`
+/// every `interval` records of each phase and once more when a phase is done, unless
+/// its last record already hit the interval.
+pub fn create_smartcore_input_with_progress<T, P, F>(
+    path: P,
+    interval: usize,
+    progress: F,
+) -> Result<(DenseMatrix<T>, Vec<T>, Vocabulary), std::io::Error>
+where
+    T: Number,
+    P: AsRef<Path>,
+    F: Fn(ProcessedCount),
+{
+    smartcore_input(path, &Progress::new(interval, &progress))
+}
+
+/// Receiver of the [`ProcessedCount`]s of the loading, tokenizing and vectorizing
+/// steps, which report to [`Progress::silent`] when called without one.
+pub(crate) struct Progress<'a> {
+    interval: usize,
+    callback: Option<&'a dyn Fn(ProcessedCount)>,
+}
+
+impl<'a> Progress<'a> {
+    pub(crate) fn new(interval: usize, callback: &'a dyn Fn(ProcessedCount)) -> Self {
+        Self {
+            interval: interval.max(1),
+            callback: Some(callback),
+        }
+    }
+
+    pub(crate) fn silent() -> Self {
+        Self {
+            interval: usize::MAX,
+            callback: None,
+        }
+    }
+
+    /// Yields `items`, reporting every `interval` of them and the last one to
+    /// `phase` as they are consumed.
+    pub(crate) fn track<'s, I>(
+        &'s self,
+        phase: Phase,
+        items: I,
+    ) -> impl Iterator<Item = I::Item> + 's
+    where
+        I: ExactSizeIterator + 's,
+    {
+        let total = items.len();
+        items.enumerate().map(move |(index, item)| {
+            let processed = index + 1;
+            let done = processed == total;
+            if let Some(callback) = self.callback {
+                if processed % self.interval == 0 || done {
+                    callback(ProcessedCount {
+                        phase,
+                        processed,
+                        done,
+                    });
+                }
+            }
+            item
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::cell::RefCell;
+
+    use crate::{create_smartcore_input, matrix_rows};
+
+    use super::{create_smartcore_input_with_progress, Phase, ProcessedCount};
+
+    #[test]
+    fn test_progress_reports() {
+        let path = std::env::temp_dir().join("sms-data-clean-progress.tsv");
+        let lines = (0..10)
+            .map(|i| {
+                format!(
+                    "{}\tmessage number {i}\n",
+                    if i % 2 == 0 { "ham" } else { "spam" }
+                )
+            })
+            .collect::<String>();
+        std::fs::write(&path, lines).expect("failed to write dataset");
+
+        let reports = RefCell::new(Vec::new());
+        let (x, y, vocabulary) = create_smartcore_input_with_progress::<usize, _, _>(
+            &path,
+            4,
+            |count: ProcessedCount| reports.borrow_mut().push(count),
+        )
+        .expect("failed to load");
+
+        let reports = reports.into_inner();
+        assert_eq!(reports.len(), 9);
+        for (phase, reports) in [Phase::Loading, Phase::Tokenizing, Phase::Vectorizing]
+            .iter()
+            .zip(reports.chunks(3))
+        {
+            let processed = reports
+                .iter()
+                .map(|count| {
+                    assert_eq!(count.phase, *phase);
+                    count.processed
+                })
+                .collect::<Vec<_>>();
+            assert_eq!(processed, vec![4, 8, 10]);
+            assert!(reports[2].done && !reports[1].done);
+        }
+
+        let (expected_x, expected_y, expected_vocabulary) =
+            create_smartcore_input::<usize, _>(&path).expect("failed to load");
+        assert_eq!(vocabulary, expected_vocabulary);
+        assert_eq!(y, expected_y);
+        assert_eq!(matrix_rows(&x), matrix_rows(&expected_x));
+    }
+}
@@ -0,0 +1,249 @@
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Write};
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{roc_curve, ConfusionMatrix, Label, RawDataset, TextClassifier};
+
+/// Version of the [`EvaluationReport`] JSON schema, bumped whenever a field is
+/// renamed, removed or changes meaning.
+pub const REPORT_SCHEMA_VERSION: u32 = 1;
+
+/// Metrics of a classifier on a labeled test set, written as JSON with
+/// [`EvaluationReport::save`] for CI dashboards to track over time.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EvaluationReport {
+    pub schema_version: u32,
+    pub total: usize,
+    pub accuracy: f64,
+    /// Area under the ROC curve of the spam probabilities, see [`roc_curve`].
+    pub auc: f64,
+    /// Labels seen in the test set or the predictions, in ascending order.
+    pub classes: Vec<String>,
+    /// `confusion[t][p]` messages of class `classes[t]` predicted as `classes[p]`.
+    pub confusion: Vec<Vec<usize>>,
+    /// One entry per label of `classes`, in the same order.
+    pub per_class: Vec<ClassReport>,
+}
+
+/// Precision, recall and F1 of one class of an [`EvaluationReport`].
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ClassReport {
+    pub label: String,
+    pub precision: f64,
+    pub recall: f64,
+    pub f1: f64,
+    /// Messages of this class in the test set.
+    pub support: usize,
+}
+
+impl EvaluationReport {
+    /// Panics unless `truth`, `predicted` and `spam_probabilities` have the same
+    /// length.
+    pub fn new(truth: &[Label], predicted: &[Label], spam_probabilities: &[f64]) -> Self {
+        let matrix = ConfusionMatrix::new(truth, predicted);
+        let spam = truth
+            .iter()
+            .map(|label| *label == Label::Spam)
+            .collect::<Vec<_>>();
+        let classes = matrix.classes();
+        Self {
+            schema_version: REPORT_SCHEMA_VERSION,
+            total: matrix.total(),
+            accuracy: matrix.accuracy(),
+            auc: roc_curve(spam_probabilities, &spam).auc,
+            classes: classes.iter().map(ToString::to_string).collect(),
+            confusion: classes
+                .iter()
+                .map(|truth| {
+                    classes
+                        .iter()
+                        .map(|predicted| matrix.count(*truth, *predicted))
+                        .collect()
+                })
+                .collect(),
+            per_class: classes
+                .iter()
+                .map(|class| ClassReport {
+                    label: class.to_string(),
+                    precision: matrix.precision(*class),
+                    recall: matrix.recall(*class),
+                    f1: matrix.f1(*class),
+                    support: matrix.true_positives(*class) + matrix.false_negatives(*class),
+                })
+                .collect(),
+        }
+    }
+
+    /// Scores `classifier` on every message of `test`.
+    pub fn evaluate<C: TextClassifier + ?Sized>(
+        classifier: &C,
+        test: &RawDataset,
+    ) -> Result<Self, std::io::Error> {
+        let mut predicted = Vec::with_capacity(test.len());
+        let mut spam_probabilities = Vec::with_capacity(test.len());
+        for data in &test.data {
+            let prediction = classifier
+                .predict(&data.sms)
+                .map_err(|err| std::io::Error::new(std::io::ErrorKind::Other, err.to_string()))?;
+            predicted.push(if prediction.spam {
+                Label::Spam
+            } else {
+                Label::Ham
+            });
+            spam_probabilities.push(prediction.spam_probability);
+        }
+        let truth = test.data.iter().map(|data| data.label).collect::<Vec<_>>();
+        Ok(Self::new(&truth, &predicted, &spam_probabilities))
+    }
+
+    /// Writes the report as pretty-printed JSON.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), std::io::Error> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        serde_json::to_writer_pretty(&mut writer, self)?;
+        writer.flush()
+    }
+
+    /// Reads a report written by [`EvaluationReport::save`]. Fails on reports of
+    /// another schema version.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, std::io::Error> {
+        let report: Self = serde_json::from_reader(BufReader::new(File::open(path)?))?;
+        if report.schema_version != REPORT_SCHEMA_VERSION {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                format!(
+                    "report schema version {} is not the supported {}",
+                    report.schema_version, REPORT_SCHEMA_VERSION
+                ),
+            ));
+        }
+        Ok(report)
+    }
+}
+
+/// A test message the model got wrong, see [`confident_mistakes`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct Mistake {
+    /// Position of the message in the test set.
+    pub index: usize,
+    pub sms: String,
+    /// The label of the test set, the one the model disagreed with.
+    pub label: Label,
+    pub spam_probability: f64,
+    /// Probability the model gave to the wrong class.
+    pub confidence: f64,
+}
+
+/// The messages of `test` that `classifier` gets wrong, most confident first, i.e.
+/// ordered by the probability given to the wrong class. The top ones are worth
+/// checking for labeling errors before blaming the model.
+pub fn confident_mistakes<C: TextClassifier + ?Sized>(
+    classifier: &C,
+    test: &RawDataset,
+) -> Result<Vec<Mistake>, std::io::Error> {
+    let mut mistakes = Vec::new();
+    for (index, data) in test.data.iter().enumerate() {
+        let prediction = classifier
+            .predict(&data.sms)
+            .map_err(|err| std::io::Error::new(std::io::ErrorKind::Other, err.to_string()))?;
+        if prediction.spam == (data.label == Label::Spam) {
+            continue;
+        }
+        mistakes.push(Mistake {
+            index,
+            sms: data.sms.clone(),
+            label: data.label,
+            spam_probability: prediction.spam_probability,
+            confidence: if prediction.spam {
+                prediction.spam_probability
+            } else {
+                1.0 - prediction.spam_probability
+            },
+        });
+    }
+    mistakes.sort_by(|a, b| b.confidence.total_cmp(&a.confidence));
+    Ok(mistakes)
+}
+
+#[cfg(test)]
+mod test {
+    use crate::fixtures;
+    use crate::Label::{Ham, Spam};
+    use crate::{RawData, RawDataset};
+
+    use super::{confident_mistakes, EvaluationReport, REPORT_SCHEMA_VERSION};
+
+    #[test]
+    fn test_report() {
+        let report = EvaluationReport::new(
+            &[Spam, Spam, Ham, Ham, Ham],
+            &[Spam, Ham, Ham, Ham, Spam],
+            &[0.9, 0.4, 0.1, 0.2, 0.6],
+        );
+        assert_eq!(report.schema_version, REPORT_SCHEMA_VERSION);
+        assert_eq!(report.total, 5);
+        assert!((report.accuracy - 0.6).abs() < 1e-12);
+        assert_eq!(report.classes, vec!["ham", "spam"]);
+        assert_eq!(report.confusion, vec![vec![2, 1], vec![1, 1]]);
+        assert_eq!(report.per_class[1].label, "spam");
+        assert_eq!(report.per_class[1].support, 2);
+        assert!((report.per_class[1].precision - 0.5).abs() < 1e-12);
+        assert!((report.auc - 5.0 / 6.0).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_confident_mistakes() {
+        let test = RawDataset {
+            data: vec![
+                RawData::new(Spam, "win a free prize"),
+                RawData::new(Spam, "call me about lunch"),
+                // Mislabeled.
+                RawData::new(Ham, "win a free prize now claim your free cash entry"),
+                RawData::new(Ham, "see you at lunch"),
+            ],
+        };
+        let mistakes = confident_mistakes(&fixtures::classifier(), &test).expect("failed to score");
+
+        assert_eq!(mistakes[0].index, 2);
+        assert_eq!(mistakes[0].label, Ham);
+        assert!(mistakes[0].confidence > 0.5);
+        assert_eq!(mistakes[0].confidence, mistakes[0].spam_probability);
+        assert!(mistakes
+            .iter()
+            .all(|mistake| mistake.index != 0 && mistake.index != 3));
+        assert!(mistakes
+            .windows(2)
+            .all(|pair| pair[0].confidence >= pair[1].confidence));
+    }
+
+    #[test]
+    fn test_report_round_trip() {
+        let test = RawDataset {
+            data: vec![
+                RawData::new(Spam, "free prize inside"),
+                RawData::new(Ham, "see you at lunch"),
+                RawData::new(Ham, "call me when you are home"),
+            ],
+        };
+        let report =
+            EvaluationReport::evaluate(&fixtures::classifier(), &test).expect("failed to score");
+        assert_eq!(report.total, 3);
+
+        let dir = std::env::temp_dir().join("sms-data-clean-report");
+        std::fs::create_dir_all(&dir).expect("failed to create temp dir");
+        let path = dir.join("report.json");
+        report.save(&path).expect("failed to save");
+        assert_eq!(
+            EvaluationReport::load(&path).expect("failed to load"),
+            report
+        );
+
+        let json = std::fs::read_to_string(&path).unwrap();
+        let mut value = serde_json::from_str::<serde_json::Value>(&json).unwrap();
+        assert_eq!(value["schema_version"], REPORT_SCHEMA_VERSION);
+        value["schema_version"] = (REPORT_SCHEMA_VERSION + 1).into();
+        std::fs::write(&path, value.to_string()).unwrap();
+        assert!(EvaluationReport::load(&path).is_err());
+    }
+}
@@ -0,0 +1,50 @@
+use crate::{Decision, Prediction};
+
+/// Keyword rules overriding the model.
+///
+/// Keywords are matched case-insensitively anywhere in the raw message, so they can
+/// hold URLs or fixed phrases of a sender template. They deliberately bypass the
+/// [`Tokenizer`](crate::Tokenizer), which strips the punctuation of a URL and drops
+/// the stop words of a phrase, and so apply to messages in any language. A
+/// blocklist hit wins over an allowlist hit.
+#[derive(Debug, Default, Clone)]
+pub struct KeywordRules {
+    /// Messages containing any of these are spam.
+    pub blocklist: Vec<String>,
+    /// Messages containing any of these are never spam.
+    pub allowlist: Vec<String>,
+}
+
+impl KeywordRules {
+    pub fn is_empty(&self) -> bool {
+        self.blocklist.is_empty() && self.allowlist.is_empty()
+    }
+
+    /// The verdict of the first matching rule, `None` to leave it to the model.
+    pub fn decide(&self, text: &str) -> Option<Prediction> {
+        if self.is_empty() {
+            return None;
+        }
+        let text = text.to_lowercase();
+        let matches = |keywords: &[String]| {
+            keywords
+                .iter()
+                .any(|keyword| text.contains(&keyword.to_lowercase()))
+        };
+        if matches(&self.blocklist) {
+            Some(Prediction {
+                spam: true,
+                spam_probability: 1.0,
+                decided_by: Decision::Blocklist,
+            })
+        } else if matches(&self.allowlist) {
+            Some(Prediction {
+                spam: false,
+                spam_probability: 0.0,
+                decided_by: Decision::Allowlist,
+            })
+        } else {
+            None
+        }
+    }
+}
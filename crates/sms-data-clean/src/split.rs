@@ -0,0 +1,295 @@
+use std::path::Path;
+
+use smartcore::linalg::basic::matrix::DenseMatrix;
+use smartcore::numbers::basenum::Number;
+
+use crate::{matrix_rows, preprocess_file, Label, RawDataset, Vocabulary};
+
+/// Features of a published train/test split, see [`create_train_test_input`].
+#[derive(Debug)]
+pub struct TrainTestInput<T: Number> {
+    pub x_train: DenseMatrix<T>,
+    pub y_train: Vec<T>,
+    pub x_test: DenseMatrix<T>,
+    pub y_test: Vec<T>,
+    /// Built from the train file only.
+    pub vocabulary: Vocabulary,
+}
+
+impl RawDataset {
+    /// Loads a split shipped as separate train and test files, see [`RawDataset::from_file`].
+    pub fn from_train_test<P, Q>(train: P, test: Q) -> Result<(Self, Self), std::io::Error>
+    where
+        P: AsRef<Path>,
+        Q: AsRef<Path>,
+    {
+        Ok((Self::from_file(train)?, Self::from_file(test)?))
+    }
+
+    /// Keeps every spam message and `round(ham_ratio * ham count)` randomly chosen ham
+    /// messages, to train faster when ham vastly outnumbers spam.
+    ///
+    /// The sample is drawn with a generator seeded by `seed` and keeps the original
+    /// order. Training on it inflates the spam prior, so pass the returned
+    /// [`HamSample::spam_prior`] to
+    /// [`Classifier::with_spam_prior`](crate::Classifier::with_spam_prior) to keep the
+    /// model calibrated. Fails unless `ham_ratio` is between 0 and 1.
+    pub fn sample_ham(self, ham_ratio: f64, seed: u64) -> Result<HamSample, std::io::Error> {
+        if !(0.0..=1.0).contains(&ham_ratio) {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                format!("invalid ham ratio {ham_ratio}, expected a fraction between 0 and 1"),
+            ));
+        }
+        let total = self.len();
+        let mut ham = (0..total)
+            .filter(|index| self.data[*index].label == Label::Ham)
+            .collect::<Vec<_>>();
+        let spam = total - ham.len();
+
+        let mut rng = SplitMix64(seed);
+        for i in (1..ham.len()).rev() {
+            ham.swap(i, (rng.next() % (i as u64 + 1)) as usize);
+        }
+        let n_ham = (ham.len() as f64 * ham_ratio).round() as usize;
+        let mut keep = vec![false; total];
+        for index in &ham[..n_ham] {
+            keep[*index] = true;
+        }
+
+        let data = self
+            .data
+            .into_iter()
+            .zip(keep)
+            .filter(|(data, keep)| *keep || data.label == Label::Spam)
+            .map(|(data, _)| data)
+            .collect();
+        Ok(HamSample {
+            dataset: Self { data },
+            spam_prior: if total == 0 {
+                0.0
+            } else {
+                spam as f64 / total as f64
+            },
+        })
+    }
+}
+
+/// Result of [`RawDataset::sample_ham`].
+#[derive(Debug)]
+pub struct HamSample {
+    pub dataset: RawDataset,
+    /// Fraction of spam before sampling, the prior the model should score with.
+    pub spam_prior: f64,
+}
+
+/// Like [`create_smartcore_input`](crate::create_smartcore_input) for a pre-split
+/// train and test file pair.
+///
+/// The vocabulary is built from the train file alone and the test file is vectorized
+/// against it, so tokens only seen in test never become features. Records left
+/// without tokens are dropped from both files.
+pub fn create_train_test_input<T: Number, P: AsRef<Path>, Q: AsRef<Path>>(
+    train: P,
+    test: Q,
+) -> Result<TrainTestInput<T>, std::io::Error> {
+    let (x_train, y_train, vocabulary) = preprocess_file(train)?.drop_empty().to_smartcore()?;
+    let (x_test, y_test) = preprocess_file(test)?
+        .drop_empty()
+        .to_smartcore_with_vocabulary(&vocabulary)?;
+    Ok(TrainTestInput {
+        x_train,
+        y_train,
+        x_test,
+        y_test,
+        vocabulary,
+    })
+}
+
+/// Like smartcore's `train_test_split`, but splits every class separately so the
+/// class proportions of `y` are preserved in both halves.
+///
+/// Each class contributes `round(test_size * class size)` rows to the test half. Rows
+/// are shuffled within their class with a generator seeded by `seed`, so the same
+/// seed always produces the same split. Returns `(x_train, x_test, y_train, y_test)`.
+///
+/// Fails unless `test_size` is strictly between 0 and 1, or when either half would
+/// be left without rows.
+pub fn stratified_split<T: Number>(
+    x: &DenseMatrix<T>,
+    y: &[T],
+    test_size: f32,
+    seed: u64,
+) -> Result<(DenseMatrix<T>, DenseMatrix<T>, Vec<T>, Vec<T>), std::io::Error> {
+    let (train, test) = stratified_indices(y, test_size, seed)?;
+
+    let rows = matrix_rows(x);
+    let select = |indices: &[usize]| {
+        let x = indices
+            .iter()
+            .map(|index| rows[*index].clone())
+            .collect::<Vec<_>>();
+        let y = indices.iter().map(|index| y[*index]).collect::<Vec<_>>();
+        (DenseMatrix::from_2d_vec(&x), y)
+    };
+    let (x_train, y_train) = select(&train);
+    let (x_test, y_test) = select(&test);
+    Ok((x_train, x_test, y_train, y_test))
+}
+
+/// Row indices of the train and test halves of [`stratified_split`].
+pub(crate) fn stratified_indices<L: PartialEq + Copy>(
+    y: &[L],
+    test_size: f32,
+    seed: u64,
+) -> Result<(Vec<usize>, Vec<usize>), std::io::Error> {
+    if !(test_size > 0.0 && test_size < 1.0) {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidInput,
+            format!("invalid test size {test_size}, expected a fraction between 0 and 1"),
+        ));
+    }
+    let mut classes: Vec<(L, Vec<usize>)> = Vec::new();
+    for (index, label) in y.iter().enumerate() {
+        match classes.iter_mut().find(|(class, _)| class == label) {
+            Some((_, indices)) => indices.push(index),
+            None => classes.push((*label, vec![index])),
+        }
+    }
+
+    let mut rng = SplitMix64(seed);
+    let (mut train, mut test) = (Vec::new(), Vec::new());
+    for (_, mut indices) in classes {
+        for i in (1..indices.len()).rev() {
+            indices.swap(i, (rng.next() % (i as u64 + 1)) as usize);
+        }
+        let n_test = (indices.len() as f32 * test_size).round() as usize;
+        test.extend_from_slice(&indices[..n_test]);
+        train.extend_from_slice(&indices[n_test..]);
+    }
+    for (half, indices) in [("train", &train), ("test", &test)] {
+        if indices.is_empty() {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                format!(
+                    "test size {test_size} leaves the {half} half of {} rows empty",
+                    y.len()
+                ),
+            ));
+        }
+    }
+    Ok((train, test))
+}
+
+/// Small deterministic generator, good enough to shuffle a dataset.
+struct SplitMix64(u64);
+
+impl SplitMix64 {
+    fn next(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use smartcore::linalg::basic::arrays::Array;
+
+    use crate::{create_smartcore_input, Label, RawData, RawDataset};
+
+    use super::{create_train_test_input, stratified_split, HamSample};
+
+    fn spam_fraction(y: &[usize]) -> f64 {
+        y.iter().filter(|y| **y == 1).count() as f64 / y.len() as f64
+    }
+
+    #[test]
+    fn test_stratified_split() {
+        let (x, y, _) =
+            create_smartcore_input::<usize, _>("../../SMSSpamCollection").expect("failed to init");
+        let (x_train, x_test, y_train, y_test) =
+            stratified_split(&x, &y, 0.3, 10).expect("failed to split");
+
+        assert_eq!(y_train.len() + y_test.len(), y.len());
+        assert_eq!(x_train.shape().0, y_train.len());
+        assert_eq!(x_test.shape().0, y_test.len());
+        let overall = spam_fraction(&y);
+        assert!((spam_fraction(&y_train) - overall).abs() < 0.005);
+        assert!((spam_fraction(&y_test) - overall).abs() < 0.005);
+
+        let (_, _, _, again) = stratified_split(&x, &y, 0.3, 10).expect("failed to split");
+        assert_eq!(again, y_test);
+
+        for test_size in [0.0, 1.0, -0.3, f32::NAN] {
+            let err = stratified_split(&x, &y, test_size, 10).unwrap_err();
+            assert_eq!(err.kind(), std::io::ErrorKind::InvalidInput);
+        }
+        // One row of each class rounds to an empty test half.
+        let err = stratified_split(&x, &[0, 1], 0.3, 10).unwrap_err();
+        assert!(err.to_string().contains("test half"), "{err}");
+    }
+
+    #[test]
+    fn test_sample_ham() {
+        let dataset = || {
+            let spam = (0..10).map(|i| RawData::new(Label::Spam, format!("free prize {i}")));
+            let ham = (0..90).map(|i| RawData::new(Label::Ham, format!("see you at {i}")));
+            RawDataset {
+                data: spam.chain(ham).collect(),
+            }
+        };
+        let count = |dataset: &RawDataset, label: Label| {
+            dataset
+                .data
+                .iter()
+                .filter(|data| data.label == label)
+                .count()
+        };
+
+        let sample = dataset().sample_ham(0.2, 3).expect("failed to sample");
+        assert_eq!(count(&sample.dataset, Label::Spam), 10);
+        assert_eq!(count(&sample.dataset, Label::Ham), 18);
+        assert!((sample.spam_prior - 0.1).abs() < 1e-12);
+
+        let again = dataset().sample_ham(0.2, 3).expect("failed to sample");
+        let messages = |sample: &HamSample| {
+            sample
+                .dataset
+                .data
+                .iter()
+                .map(|data| data.sms.clone())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(messages(&again), messages(&sample));
+
+        assert!(dataset().sample_ham(1.5, 3).is_err());
+    }
+
+    #[test]
+    fn test_train_test_files() {
+        let dir = std::env::temp_dir().join("sms-data-clean-train-test");
+        std::fs::create_dir_all(&dir).expect("failed to create temp dir");
+        let (train, test) = (dir.join("train.tsv"), dir.join("test.tsv"));
+        std::fs::write(&train, "spam\tWin a free prize\nham\tSee you at lunch\n")
+            .expect("failed to write train");
+        std::fs::write(&test, "spam\tFree crypto giveaway\nham\tLunch tomorrow\n")
+            .expect("failed to write test");
+
+        let input = create_train_test_input::<usize, _, _>(&train, &test).expect("failed to load");
+        assert!(input.vocabulary.get("crypto").is_none());
+        assert!(input.vocabulary.get("tomorrow").is_none());
+        assert_eq!(input.x_test.shape(), (2, input.vocabulary.len()));
+        assert_eq!(input.x_train.shape().1, input.vocabulary.len());
+
+        let free = input.vocabulary.get("free").unwrap();
+        assert_eq!(*input.x_test.get((0, free)), 1);
+        let known = (0..input.vocabulary.len())
+            .map(|col| *input.x_test.get((0, col)) + *input.x_test.get((1, col)))
+            .sum::<usize>();
+        assert_eq!(known, 2);
+        assert_eq!(input.y_test, vec![1, 0]);
+    }
+}
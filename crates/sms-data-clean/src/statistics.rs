@@ -0,0 +1,278 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::{Dataset, Label, RawDataset};
+
+/// Number of documents containing a token, split by class.
+#[derive(Debug, Default, Clone, Copy)]
+struct Presence {
+    ham: usize,
+    spam: usize,
+}
+
+/// Documents with `lower..upper` tokens, split by class.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LengthBucket {
+    pub lower: usize,
+    pub upper: usize,
+    pub ham: usize,
+    pub spam: usize,
+}
+
+/// Step of the standard preprocessing, see [`RawDataset::vocabulary_sizes`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StageName {
+    Raw,
+    Lowercased,
+    PunctuationStripped,
+    StopWordsRemoved,
+}
+
+impl std::fmt::Display for StageName {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let stage = match self {
+            StageName::Raw => "raw",
+            StageName::Lowercased => "lowercased",
+            StageName::PunctuationStripped => "punctuation stripped",
+            StageName::StopWordsRemoved => "stop words removed",
+        };
+        f.pad(stage)
+    }
+}
+
+impl RawDataset {
+    /// Number of distinct whitespace separated tokens after each step of
+    /// [`Pipeline::standard`](crate::Pipeline::standard), starting with the raw
+    /// text, to see how much every transformation shrinks the vocabulary.
+    pub fn vocabulary_sizes(self) -> Vec<(StageName, usize)> {
+        fn distinct(dataset: &RawDataset) -> usize {
+            dataset
+                .data
+                .iter()
+                .flat_map(|row| row.subject.iter().chain(std::iter::once(&row.sms)))
+                .flat_map(|text| text.split_whitespace())
+                .collect::<HashSet<_>>()
+                .len()
+        }
+
+        let raw = distinct(&self);
+        let lowercased = self.lowercase();
+        let lowercased_size = distinct(&lowercased);
+        let stripped = lowercased.without_punctuaction();
+        let stripped_size = distinct(&stripped);
+        let without_stop_words = stripped
+            .tokenize()
+            .stop_words()
+            .data
+            .iter()
+            .flat_map(|data| data.tokens.iter().map(String::as_str))
+            .collect::<HashSet<_>>()
+            .len();
+
+        vec![
+            (StageName::Raw, raw),
+            (StageName::Lowercased, lowercased_size),
+            (StageName::PunctuationStripped, stripped_size),
+            (StageName::StopWordsRemoved, without_stop_words),
+        ]
+    }
+}
+
+impl Dataset {
+    /// Histogram of document lengths in tokens, in buckets of `bucket_width` tokens
+    /// from zero up to the longest document.
+    pub fn token_count_histogram(&self, bucket_width: usize) -> Vec<LengthBucket> {
+        let bucket_width = bucket_width.max(1);
+        let mut buckets: Vec<LengthBucket> = Vec::new();
+        for (label, data) in self.labels.iter().zip(&self.data) {
+            let bucket = data.tokens.len() / bucket_width;
+            while buckets.len() <= bucket {
+                let lower = buckets.len() * bucket_width;
+                buckets.push(LengthBucket {
+                    lower,
+                    upper: lower + bucket_width,
+                    ham: 0,
+                    spam: 0,
+                });
+            }
+            match label {
+                Label::Ham => buckets[bucket].ham += 1,
+                Label::Spam => buckets[bucket].spam += 1,
+            }
+        }
+        buckets
+    }
+
+    fn spam_count(&self) -> usize {
+        self.labels
+            .iter()
+            .filter(|label| **label == Label::Spam)
+            .count()
+    }
+
+    /// Spam lift of every token, `P(spam | token) / P(spam)` over the records the
+    /// token is present in. Above one the token is a spam indicator, below one a ham
+    /// indicator. Empty when the dataset has no spam, where lift is undefined.
+    pub fn token_lift(&self) -> HashMap<String, f64> {
+        let spam = self.spam_count();
+        if spam == 0 {
+            return HashMap::new();
+        }
+        let spam_rate = spam as f64 / self.len() as f64;
+        self.term_presence()
+            .into_iter()
+            .map(|(token, presence)| {
+                let spam_given_token = presence.spam as f64 / (presence.ham + presence.spam) as f64;
+                (token.to_string(), spam_given_token / spam_rate)
+            })
+            .collect()
+    }
+
+    /// [`Dataset::token_lift`] highest first, e.g. to seed a blocklist.
+    pub fn token_lift_sorted(&self) -> Vec<(String, f64)> {
+        let mut lifts = self.token_lift().into_iter().collect::<Vec<_>>();
+        lifts.sort_by(|a, b| b.1.total_cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        lifts
+    }
+
+    fn term_presence(&self) -> HashMap<&str, Presence> {
+        let mut presence: HashMap<&str, Presence> = HashMap::new();
+        for (label, data) in self.labels.iter().zip(&self.data) {
+            let tokens = data
+                .tokens
+                .iter()
+                .map(String::as_str)
+                .collect::<HashSet<_>>();
+            for token in tokens {
+                let counts = presence.entry(token).or_default();
+                match label {
+                    Label::Ham => counts.ham += 1,
+                    Label::Spam => counts.spam += 1,
+                }
+            }
+        }
+        presence
+    }
+
+    /// Mutual information, in nats, between the presence of each token and the label,
+    /// highest first.
+    pub fn mutual_information(&self) -> Vec<(String, f64)> {
+        let n = self.len() as f64;
+        let spam = self.spam_count() as f64;
+        let ham = n - spam;
+
+        let mut scores = self
+            .term_presence()
+            .into_iter()
+            .map(|(token, presence)| {
+                let present = (presence.ham + presence.spam) as f64;
+                let cells = [
+                    (presence.spam as f64, present, spam),
+                    (presence.ham as f64, present, ham),
+                    (spam - presence.spam as f64, n - present, spam),
+                    (ham - presence.ham as f64, n - present, ham),
+                ];
+                let mi = cells
+                    .iter()
+                    .filter(|(joint, _, _)| *joint > 0.0)
+                    .map(|(joint, term, class)| joint / n * (n * joint / (term * class)).ln())
+                    .sum::<f64>();
+                (token.to_string(), mi)
+            })
+            .collect::<Vec<_>>();
+        scores.sort_by(|a, b| b.1.total_cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        scores
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::fixtures::dataset;
+    use crate::{Label, RawDataset};
+
+    use super::{LengthBucket, StageName};
+
+    #[test]
+    fn test_mutual_information() {
+        let dataset = dataset(&[
+            (Label::Spam, "winner call now"),
+            (Label::Spam, "winner claim now"),
+            (Label::Ham, "call me now"),
+            (Label::Ham, "see you now"),
+        ]);
+        let scores = dataset.mutual_information();
+
+        assert_eq!(scores[0].0, "winner");
+        assert!((scores[0].1 - 2f64.ln()).abs() < 1e-9);
+        let now = scores.iter().find(|(token, _)| token == "now").unwrap();
+        assert!(now.1.abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_token_lift() {
+        let corpus = dataset(&[
+            (Label::Spam, "winner call now"),
+            (Label::Ham, "call me now"),
+            (Label::Ham, "see you now"),
+            (Label::Ham, "see you later"),
+        ]);
+        let lift = corpus.token_lift();
+
+        assert!((lift["winner"] - 4.0).abs() < 1e-9);
+        assert!((lift["call"] - 2.0).abs() < 1e-9);
+        assert_eq!(lift["see"], 0.0);
+
+        let sorted = corpus.token_lift_sorted();
+        assert_eq!(sorted.len(), lift.len());
+        assert_eq!(sorted[0].0, "winner");
+        assert!(sorted.windows(2).all(|pair| pair[0].1 >= pair[1].1));
+
+        assert!(dataset(&[(Label::Ham, "see you later")])
+            .token_lift()
+            .is_empty());
+    }
+
+    #[test]
+    fn test_vocabulary_sizes() {
+        let sizes = RawDataset::from_file("../../SMSSpamCollection")
+            .expect("failed to load")
+            .vocabulary_sizes();
+
+        let stages = sizes.iter().map(|(stage, _)| *stage).collect::<Vec<_>>();
+        assert_eq!(
+            stages,
+            vec![
+                StageName::Raw,
+                StageName::Lowercased,
+                StageName::PunctuationStripped,
+                StageName::StopWordsRemoved,
+            ]
+        );
+        assert!(
+            sizes.windows(2).all(|pair| pair[0].1 > pair[1].1),
+            "{:?}",
+            sizes
+        );
+    }
+
+    #[test]
+    fn test_token_count_histogram() {
+        let dataset = dataset(&[
+            (Label::Ham, "ok"),
+            (Label::Ham, "see you at lunch"),
+            (Label::Ham, "on my way"),
+            (Label::Spam, "win a free prize now call"),
+            (Label::Spam, "claim your free cash prize today"),
+        ]);
+        let bucket = |lower, ham, spam| LengthBucket {
+            lower,
+            upper: lower + 3,
+            ham,
+            spam,
+        };
+
+        assert_eq!(
+            dataset.token_count_histogram(3),
+            vec![bucket(0, 1, 0), bucket(3, 2, 0), bucket(6, 0, 2)]
+        );
+    }
+}
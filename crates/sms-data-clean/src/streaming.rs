@@ -0,0 +1,102 @@
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use std::path::Path;
+
+use smartcore::linalg::basic::matrix::DenseMatrix;
+use smartcore::numbers::basenum::Number;
+
+use crate::vocabulary::DocumentFrequencies;
+use crate::{
+    ensure_vocabulary, parse_record, strip_bom, try_bag_of_words, Dataset, Label, LabelEncoder,
+    Pipeline, RawDataset, VocabConfig, Vocabulary,
+};
+
+/// Like [`create_smartcore_input`](crate::create_smartcore_input) with `config`, without
+/// holding the raw or tokenized corpus in memory.
+///
+/// The `min_df` and `max_df` limits need document frequencies over the whole corpus
+/// before the first row can be vectorized, so the file is read and preprocessed twice:
+/// once to count document frequencies, once to build the feature rows against the
+/// pruned vocabulary. Only the vocabulary and the resulting matrix are kept. Records
+/// left without tokens are skipped in both passes, as `create_smartcore_input` drops
+/// them.
+pub fn create_smartcore_input_streaming<T: Number, P: AsRef<Path>>(
+    path: P,
+    config: &VocabConfig,
+) -> Result<(DenseMatrix<T>, Vec<T>, Vocabulary), std::io::Error> {
+    let pipeline = Pipeline::standard();
+
+    let mut frequencies = DocumentFrequencies::default();
+    for_each_record(path.as_ref(), &pipeline, |dataset| {
+        for data in dataset.data.iter().filter(|data| !data.tokens.is_empty()) {
+            frequencies.add(&data.tokens);
+        }
+        Ok(())
+    })?;
+    let vocabulary = Vocabulary::from_document_frequencies(&frequencies, config);
+    ensure_vocabulary(&vocabulary)?;
+
+    let mut labels = Vec::new();
+    let mut rows = Vec::new();
+    for_each_record(path.as_ref(), &pipeline, |dataset| {
+        let dataset = dataset.drop_empty();
+        labels.extend(dataset.labels);
+        for data in dataset.data {
+            rows.push(try_bag_of_words::<T>(data.tokens, &vocabulary)?);
+        }
+        Ok(())
+    })?;
+
+    Ok((
+        DenseMatrix::from_2d_vec(&rows),
+        LabelEncoder::default().encode_all(labels),
+        vocabulary,
+    ))
+}
+
+/// Preprocesses the file one line at a time, handing every record to `f` as a
+/// single-row dataset.
+fn for_each_record<F>(path: &Path, pipeline: &Pipeline, mut f: F) -> Result<(), std::io::Error>
+where
+    F: FnMut(Dataset) -> Result<(), std::io::Error>,
+{
+    let reader = BufReader::new(File::open(path)?);
+    for (index, line) in reader.lines().enumerate() {
+        let line = line?;
+        let line = if index == 0 { strip_bom(&line) } else { &line };
+        let record = parse_record(line, |label| label.parse::<Label>().ok())?;
+        f(pipeline.run(RawDataset { data: vec![record] }))?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{matrix_rows, preprocess_file, VocabConfig, Vocabulary};
+
+    use super::create_smartcore_input_streaming;
+
+    const CORPUS: &str = "../../SMSSpamCollection";
+
+    fn config() -> VocabConfig {
+        VocabConfig::default().with_min_df(5).with_max_df(0.5)
+    }
+
+    #[test]
+    fn test_streaming_matches_in_memory() {
+        let (x, y, vocabulary) = create_smartcore_input_streaming::<usize, _>(CORPUS, &config())
+            .expect("failed to stream");
+
+        let dataset = preprocess_file(CORPUS)
+            .expect("failed to load")
+            .drop_empty();
+        assert!(vocabulary.len() < Vocabulary::from_dataset(&dataset).len());
+        let (expected_x, expected_y, expected_vocabulary) = dataset
+            .to_smartcore_with_config::<usize>(&config())
+            .expect("failed to vectorize");
+
+        assert_eq!(vocabulary, expected_vocabulary);
+        assert_eq!(y, expected_y);
+        assert_eq!(matrix_rows(&x), matrix_rows(&expected_x));
+    }
+}
@@ -0,0 +1,249 @@
+use crate::progress::Progress;
+use crate::{truncate_chars, Dataset, Phase, RawData, RawDataset, StopWords, TokenizedData};
+
+/// Turns text into tokens, the same way for training and for scoring.
+///
+/// [`Tokenizer::new`] only splits on whitespace, the other steps are opt-in and run
+/// in this order: lowercasing, deleting ASCII punctuation, splitting, dropping stop
+/// words, dropping tokens shorter than the minimum length.
+///
+/// [`Tokenizer::with_max_chars`] and [`Tokenizer::with_max_tokens`] bound the work
+/// and memory spent on a single message, e.g. a record crafted out of millions of
+/// one character tokens, by truncating the input and the output. Both are unbounded
+/// by default.
+#[derive(Debug, Clone, Default)]
+pub struct Tokenizer {
+    lowercase: bool,
+    strip_punctuation: bool,
+    stop_words: Option<StopWords>,
+    min_len: usize,
+    max_chars: Option<usize>,
+    max_tokens: Option<usize>,
+    keep_original: bool,
+}
+
+impl Tokenizer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The tokenization used to train the bundled model, equivalent to
+    /// [`Pipeline::standard`](crate::Pipeline::standard).
+    pub fn standard() -> Self {
+        Self::new()
+            .with_lowercase(true)
+            .with_strip_punctuation(true)
+            .with_stop_words(StopWords::default())
+    }
+
+    pub fn with_lowercase(mut self, lowercase: bool) -> Self {
+        self.lowercase = lowercase;
+        self
+    }
+
+    pub fn with_strip_punctuation(mut self, strip_punctuation: bool) -> Self {
+        self.strip_punctuation = strip_punctuation;
+        self
+    }
+
+    pub fn with_stop_words(mut self, stop_words: StopWords) -> Self {
+        self.stop_words = Some(stop_words);
+        self
+    }
+
+    pub fn with_min_len(mut self, min_len: usize) -> Self {
+        self.min_len = min_len;
+        self
+    }
+
+    /// Only tokenizes the first `max_chars` characters of a message, and of its
+    /// subject.
+    pub fn with_max_chars(mut self, max_chars: usize) -> Self {
+        self.max_chars = Some(max_chars);
+        self
+    }
+
+    /// Keeps at most `max_tokens` tokens per record, the subject's included.
+    pub fn with_max_tokens(mut self, max_tokens: usize) -> Self {
+        self.max_tokens = Some(max_tokens);
+        self
+    }
+
+    /// Keeps the message of every record in [`TokenizedData::original`], so token
+    /// stages and predictions can be traced back to it.
+    pub fn with_keep_original(mut self, keep_original: bool) -> Self {
+        self.keep_original = keep_original;
+        self
+    }
+
+    pub fn tokenize(&self, text: &str) -> Vec<String> {
+        let mut tokens = Vec::new();
+        self.tokenize_into(text, &mut tokens);
+        tokens
+    }
+
+    /// Tokens of the subject, if any, followed by those of the message.
+    pub fn tokenize_record(&self, data: &RawData) -> Vec<String> {
+        self.tokenize_data(data).tokens
+    }
+
+    /// Like [`Tokenizer::tokenize_record`], remembering which tokens are the subject's.
+    pub fn tokenize_data(&self, data: &RawData) -> TokenizedData {
+        let mut tokens = Vec::new();
+        if let Some(subject) = &data.subject {
+            self.tokenize_into(subject, &mut tokens);
+        }
+        let subject_len = tokens.len();
+        self.tokenize_into(&data.sms, &mut tokens);
+        TokenizedData {
+            tokens,
+            subject_len,
+            original: self.keep_original.then(|| data.sms.clone()),
+        }
+    }
+
+    fn tokenize_into(&self, text: &str, tokens: &mut Vec<String>) {
+        let remaining = self.max_tokens.map_or(usize::MAX, |max_tokens| {
+            max_tokens.saturating_sub(tokens.len())
+        });
+        let text = self.clean(text);
+        tokens.extend(self.split(&text, remaining).map(ToString::to_string));
+    }
+
+    /// `text` truncated, lowercased and stripped of punctuation as configured, ready
+    /// for [`Tokenizer::split`].
+    pub(crate) fn clean(&self, text: &str) -> String {
+        let text = match self.max_chars {
+            Some(max_chars) => truncate_chars(text, max_chars),
+            None => text,
+        };
+        let mut text = if self.lowercase {
+            text.to_lowercase()
+        } else {
+            text.to_string()
+        };
+        if self.strip_punctuation {
+            text.retain(|c| !c.is_ascii_punctuation());
+        }
+        text
+    }
+
+    /// At most `limit` tokens of a [`Tokenizer::clean`] text.
+    fn split<'a>(&'a self, text: &'a str, limit: usize) -> impl Iterator<Item = &'a str> {
+        text.split_whitespace()
+            .filter(|token| {
+                self.stop_words
+                    .as_ref()
+                    .is_none_or(|stop_words| !stop_words.contains(token))
+            })
+            .filter(|token| token.chars().count() >= self.min_len)
+            .take(limit)
+    }
+
+    /// The tokens [`Tokenizer::tokenize`] returns for a message, borrowed from its
+    /// [`Tokenizer::clean`] text instead of allocated one by one.
+    pub(crate) fn tokens<'a>(&'a self, text: &'a str) -> impl Iterator<Item = &'a str> {
+        self.split(text, self.max_tokens.unwrap_or(usize::MAX))
+    }
+}
+
+impl RawDataset {
+    pub fn tokenize_with(self, tokenizer: &Tokenizer) -> Dataset {
+        self.tokenize_with_progress(tokenizer, &Progress::silent())
+    }
+
+    /// Like [`RawDataset::tokenize_with`], reporting every record to `progress`.
+    pub(crate) fn tokenize_with_progress(
+        self,
+        tokenizer: &Tokenizer,
+        progress: &Progress,
+    ) -> Dataset {
+        let (labels, data) = progress
+            .track(Phase::Tokenizing, self.data.into_iter())
+            .map(|row| (row.label, tokenizer.tokenize_data(&row)))
+            .unzip();
+        Dataset { labels, data }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{fixtures, Label, RawData, RawDataset};
+
+    use super::Tokenizer;
+
+    #[test]
+    fn test_training_and_inference_agree() {
+        let text = "WINNER!! You have been selected for a free £900 prize, call now.";
+        let tokenizer = Tokenizer::standard();
+
+        let training = RawDataset {
+            data: vec![RawData::new(Label::Spam, text)],
+        }
+        .lowercase()
+        .without_punctuaction()
+        .tokenize()
+        .stop_words();
+        assert_eq!(tokenizer.tokenize(text), training.data[0].tokens);
+
+        let classifier = fixtures::classifier();
+        assert_eq!(classifier.tokenize(text), tokenizer.tokenize(text));
+        assert_eq!(
+            tokenizer.tokenize(text)[..3],
+            ["winner", "selected", "free"]
+        );
+    }
+
+    #[test]
+    fn test_bounded_output() {
+        let pathological = "x ".repeat(1_000_000);
+        let tokenizer = Tokenizer::standard().with_max_tokens(100);
+        assert_eq!(tokenizer.tokenize(&pathological).len(), 100);
+
+        let record = RawData {
+            subject: Some("win win win".to_string()),
+            ..RawData::new(Label::Spam, "free prize now")
+        };
+        let tokens = Tokenizer::new().with_max_tokens(4).tokenize_record(&record);
+        assert_eq!(tokens, vec!["win", "win", "win", "free"]);
+
+        let tokenizer = Tokenizer::new().with_max_chars(12);
+        assert_eq!(
+            tokenizer.tokenize("free prize now"),
+            vec!["free", "prize", "n"]
+        );
+        assert_eq!(tokenizer.tokenize(&pathological).len(), 6);
+    }
+
+    #[test]
+    fn test_keep_original() {
+        let text = "Call me when you are home";
+        let dataset = RawDataset {
+            data: vec![RawData::new(Label::Ham, text)],
+        };
+        let dataset = dataset
+            .tokenize_with(
+                &Tokenizer::new()
+                    .with_lowercase(true)
+                    .with_keep_original(true),
+            )
+            .stop_words();
+
+        assert_eq!(dataset.data[0].tokens, vec!["call", "home"]);
+        assert_eq!(dataset.data[0].original.as_deref(), Some(text));
+
+        let dataset = RawDataset {
+            data: vec![RawData::new(Label::Ham, text)],
+        };
+        assert_eq!(
+            dataset.tokenize_with(&Tokenizer::new()).data[0].original,
+            None
+        );
+    }
+
+    #[test]
+    fn test_min_len() {
+        let tokenizer = Tokenizer::new().with_min_len(3);
+        assert_eq!(tokenizer.tokenize("ok Hi FREE"), vec!["FREE"]);
+    }
+}
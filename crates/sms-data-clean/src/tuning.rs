@@ -0,0 +1,231 @@
+use smartcore::naive_bayes::multinomial::MultinomialNB;
+
+use crate::codegen::DEFAULT_SPLIT_SEED;
+use crate::split::stratified_indices;
+use crate::{ConfusionMatrix, Dataset, Label, LabelEncoder, VocabConfig, Vocabulary};
+
+/// Candidate vocabulary pruning thresholds of [`search_df_thresholds`], every
+/// `min_df` combined with every `max_df`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DfGrid {
+    pub min_df: Vec<usize>,
+    pub max_df: Vec<f64>,
+    /// Fraction of every class held out for validation.
+    pub test_size: f32,
+    /// Seed of the stratified train/validation split.
+    pub seed: u64,
+}
+
+impl Default for DfGrid {
+    fn default() -> Self {
+        Self {
+            min_df: vec![1, 2, 3, 5],
+            max_df: vec![0.5, 0.8, 1.0],
+            test_size: 0.3,
+            seed: DEFAULT_SPLIT_SEED,
+        }
+    }
+}
+
+impl DfGrid {
+    pub fn with_min_df(mut self, min_df: Vec<usize>) -> Self {
+        self.min_df = min_df;
+        self
+    }
+
+    pub fn with_max_df(mut self, max_df: Vec<f64>) -> Self {
+        self.max_df = max_df;
+        self
+    }
+
+    /// `test_size` must leave both the training and the validation part with rows,
+    /// otherwise [`search_df_thresholds`] fails.
+    pub fn with_test_size(mut self, test_size: f32) -> Self {
+        self.test_size = test_size;
+        self
+    }
+
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.seed = seed;
+        self
+    }
+}
+
+/// Validation spam F1 of a model trained with one pair of thresholds.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DfScore {
+    pub min_df: usize,
+    pub max_df: f64,
+    pub f1: f64,
+}
+
+/// Every pair of a [`DfGrid`] with its score, in grid order, and the best of them.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DfSearch {
+    pub best: DfScore,
+    pub scores: Vec<DfScore>,
+}
+
+/// Retrains a Naive Bayes model for every `(min_df, max_df)` pair of `grid` on the
+/// same stratified split of `dataset`, scoring each by spam F1 on the held-out part.
+///
+/// The vocabulary of every pair is built from the training part alone. Pairs that
+/// prune away every token score zero. Of equal scores, the pair listed first in the
+/// grid wins. Fails before training anything when the grid has no pairs, a
+/// `max_df` is not a fraction in `(0, 1]` or `test_size` leaves either part of the
+/// split empty.
+pub fn search_df_thresholds(dataset: &Dataset, grid: &DfGrid) -> Result<DfSearch, std::io::Error> {
+    if grid.min_df.is_empty() || grid.max_df.is_empty() {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidInput,
+            "threshold grid has no (min_df, max_df) pairs",
+        ));
+    }
+    if let Some(max_df) = grid
+        .max_df
+        .iter()
+        .find(|max_df| !(**max_df > 0.0 && **max_df <= 1.0))
+    {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidInput,
+            format!("invalid max_df {max_df}, expected a fraction in (0, 1]"),
+        ));
+    }
+    let (train, test) = stratified_indices(&dataset.labels, grid.test_size, grid.seed)?;
+    let select = |indices: &[usize]| Dataset {
+        labels: indices.iter().map(|index| dataset.labels[*index]).collect(),
+        data: indices
+            .iter()
+            .map(|index| dataset.data[*index].clone())
+            .collect(),
+    };
+    let (train, test) = (select(&train), select(&test));
+
+    let mut scores = Vec::new();
+    for min_df in &grid.min_df {
+        for max_df in &grid.max_df {
+            let config = VocabConfig::default()
+                .with_min_df(*min_df)
+                .with_max_df(*max_df);
+            let vocabulary = Vocabulary::build(&train, &config);
+            let f1 = if vocabulary.is_empty() {
+                0.0
+            } else {
+                validation_f1(&train, &test, &vocabulary)?
+            };
+            scores.push(DfScore {
+                min_df: *min_df,
+                max_df: *max_df,
+                f1,
+            });
+        }
+    }
+
+    let best = scores
+        .iter()
+        .copied()
+        .reduce(|best, score| if score.f1 > best.f1 { score } else { best })
+        .expect("the grid has pairs");
+    Ok(DfSearch { best, scores })
+}
+
+fn validation_f1(
+    train: &Dataset,
+    test: &Dataset,
+    vocabulary: &Vocabulary,
+) -> Result<f64, std::io::Error> {
+    let vectorize = |dataset: &Dataset| {
+        Dataset {
+            labels: dataset.labels.clone(),
+            data: dataset.data.clone(),
+        }
+        .to_smartcore_with_vocabulary::<usize>(vocabulary)
+    };
+    let (x_train, y_train) = vectorize(train)?;
+    let (x_test, _) = vectorize(test)?;
+
+    let model = MultinomialNB::fit(&x_train, &y_train, Default::default())
+        .map_err(|err| std::io::Error::new(std::io::ErrorKind::Other, err.to_string()))?;
+    let encoder = LabelEncoder::default();
+    let predicted = model
+        .predict(&x_test)
+        .map_err(|err| std::io::Error::new(std::io::ErrorKind::Other, err.to_string()))?
+        .into_iter()
+        .map(|y| encoder.decode(y).expect("labels are encoded as 0 or 1"))
+        .collect::<Vec<_>>();
+    Ok(ConfusionMatrix::new(&test.labels, &predicted).f1(Label::Spam))
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{fixtures, Label};
+
+    use super::{search_df_thresholds, DfGrid};
+
+    #[test]
+    fn test_search_df_thresholds() {
+        let mut rows = Vec::new();
+        for i in 0..10 {
+            rows.push((Label::Spam, format!("win a free prize now offer{i}")));
+            rows.push((Label::Spam, format!("claim your free cash prize code{i}")));
+            rows.push((Label::Ham, format!("see you at lunch today friend{i}")));
+            rows.push((Label::Ham, format!("call me when you get home later{i}")));
+        }
+        let rows = rows
+            .iter()
+            .map(|(label, sms)| (*label, sms.as_str()))
+            .collect::<Vec<_>>();
+        let dataset = fixtures::dataset(&rows);
+
+        let grid = DfGrid::default()
+            .with_min_df(vec![1, 2, 100])
+            .with_max_df(vec![0.3, 1.0])
+            .with_seed(7);
+        let search = search_df_thresholds(&dataset, &grid).expect("failed to search");
+
+        assert_eq!(search.scores.len(), 6);
+        let pairs = search
+            .scores
+            .iter()
+            .map(|score| (score.min_df, score.max_df))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            pairs,
+            vec![
+                (1, 0.3),
+                (1, 1.0),
+                (2, 0.3),
+                (2, 1.0),
+                (100, 0.3),
+                (100, 1.0)
+            ]
+        );
+        // Nothing appears in 100 documents, so those pairs have no features.
+        assert_eq!(search.scores[4].f1, 0.0);
+        assert!(search.best.min_df < 100);
+        assert!(search.best.f1 > 0.9, "{:?}", search);
+        assert!(search.scores.iter().all(|score| score.f1 <= search.best.f1));
+
+        let again = search_df_thresholds(&dataset, &grid).expect("failed to search");
+        assert_eq!(again, search);
+    }
+
+    #[test]
+    fn test_search_df_thresholds_invalid_grid() {
+        let dataset = fixtures::dataset(fixtures::TRAINING_ROWS);
+        let invalid = [
+            DfGrid::default().with_min_df(Vec::new()),
+            DfGrid::default().with_max_df(Vec::new()),
+            DfGrid::default().with_max_df(vec![0.5, 0.0]),
+            DfGrid::default().with_max_df(vec![f64::NAN]),
+            DfGrid::default().with_test_size(0.0),
+            DfGrid::default().with_test_size(1.0),
+            // Seven rows, none of them held out.
+            DfGrid::default().with_test_size(0.01),
+        ];
+        for grid in &invalid {
+            let err = search_df_thresholds(&dataset, grid).unwrap_err();
+            assert_eq!(err.kind(), std::io::ErrorKind::InvalidInput, "{:?}", grid);
+        }
+    }
+}
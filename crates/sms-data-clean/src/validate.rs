@@ -0,0 +1,108 @@
+use std::collections::HashSet;
+
+use crate::{Dataset, Label};
+
+/// Problem found by [`Dataset::validate`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DatasetIssue {
+    /// There are no documents at all.
+    Empty,
+    /// No document has this label, so a model cannot learn to tell the classes apart.
+    MissingClass(Label),
+    /// Indices of the documents without tokens.
+    EmptyDocuments(Vec<usize>),
+    /// Fewer than two distinct tokens across all documents.
+    DegenerateVocabulary { tokens: usize },
+}
+
+impl std::fmt::Display for DatasetIssue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DatasetIssue::Empty => write!(f, "dataset is empty"),
+            DatasetIssue::MissingClass(label) => write!(f, "no document is labeled {}", label),
+            DatasetIssue::EmptyDocuments(indices) => {
+                write!(f, "{} documents have no tokens", indices.len())
+            }
+            DatasetIssue::DegenerateVocabulary { tokens } => {
+                write!(f, "vocabulary has only {} distinct tokens", tokens)
+            }
+        }
+    }
+}
+
+impl Dataset {
+    /// Checks that the dataset is fit for training, returning every issue found.
+    ///
+    /// An empty dataset is only reported as [`DatasetIssue::Empty`].
+    pub fn validate(&self) -> Result<(), Vec<DatasetIssue>> {
+        if self.is_empty() {
+            return Err(vec![DatasetIssue::Empty]);
+        }
+
+        let mut issues = Vec::new();
+        for label in [Label::Ham, Label::Spam] {
+            if !self.labels.contains(&label) {
+                issues.push(DatasetIssue::MissingClass(label));
+            }
+        }
+
+        let empty = self
+            .data
+            .iter()
+            .enumerate()
+            .filter(|(_, data)| data.tokens.is_empty())
+            .map(|(index, _)| index)
+            .collect::<Vec<_>>();
+        if !empty.is_empty() {
+            issues.push(DatasetIssue::EmptyDocuments(empty));
+        }
+
+        let tokens = self
+            .data
+            .iter()
+            .flat_map(|data| &data.tokens)
+            .collect::<HashSet<_>>()
+            .len();
+        if tokens < 2 {
+            issues.push(DatasetIssue::DegenerateVocabulary { tokens });
+        }
+
+        if issues.is_empty() {
+            Ok(())
+        } else {
+            Err(issues)
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{fixtures, Dataset, Label};
+
+    use super::DatasetIssue;
+
+    #[test]
+    fn test_validate() {
+        let dataset = fixtures::dataset(&[(Label::Spam, "free prize"), (Label::Ham, "lunch")]);
+        assert_eq!(dataset.validate(), Ok(()));
+
+        let dataset = fixtures::dataset(&[
+            (Label::Ham, "see you at lunch"),
+            (Label::Ham, ""),
+            (Label::Ham, "call me later"),
+        ]);
+        assert_eq!(
+            dataset.validate(),
+            Err(vec![
+                DatasetIssue::MissingClass(Label::Spam),
+                DatasetIssue::EmptyDocuments(vec![1]),
+            ])
+        );
+
+        let dataset = Dataset {
+            labels: Vec::new(),
+            data: Vec::new(),
+        };
+        assert_eq!(dataset.validate(), Err(vec![DatasetIssue::Empty]));
+    }
+}
@@ -0,0 +1,531 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Write};
+use std::path::Path;
+
+use serde::{Deserialize, Serialize, Serializer};
+
+use crate::Dataset;
+
+/// Maps every known token to its column in the feature matrix.
+///
+/// Indices are assigned in order of first appearance and never renumbered, so a
+/// vocabulary can be extended with new tokens while older feature columns keep
+/// their meaning.
+///
+/// Serializes as a JSON object with keys in sorted order, so the same vocabulary
+/// always produces the same bytes. Deserializing fails unless the indices are
+/// `0..len`, each used once.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Deserialize)]
+#[serde(try_from = "HashMap<String, usize>")]
+pub struct Vocabulary {
+    index: HashMap<String, usize>,
+}
+
+/// Options applied while assigning feature indices, independently of preprocessing.
+pub struct VocabConfig {
+    /// Tokens for which this returns `false` get no feature column.
+    pub token_filter: Option<Box<dyn Fn(&str) -> bool>>,
+    /// Tokens appearing in fewer documents are dropped.
+    pub min_df: usize,
+    /// Tokens appearing in a larger fraction of the documents are dropped.
+    pub max_df: f64,
+}
+
+impl Default for VocabConfig {
+    fn default() -> Self {
+        Self {
+            token_filter: None,
+            min_df: 1,
+            max_df: 1.0,
+        }
+    }
+}
+
+impl VocabConfig {
+    pub fn with_min_df(mut self, min_df: usize) -> Self {
+        self.min_df = min_df;
+        self
+    }
+
+    pub fn with_max_df(mut self, max_df: f64) -> Self {
+        self.max_df = max_df;
+        self
+    }
+
+    pub fn with_token_filter<F>(mut self, filter: F) -> Self
+    where
+        F: Fn(&str) -> bool + 'static,
+    {
+        self.token_filter = Some(Box::new(filter));
+        self
+    }
+
+    fn accepts(&self, token: &str) -> bool {
+        self.token_filter
+            .as_ref()
+            .is_none_or(|filter| filter(token))
+    }
+
+    fn accepts_frequency(&self, document_frequency: usize, documents: usize) -> bool {
+        document_frequency >= self.min_df
+            && document_frequency as f64 <= self.max_df * documents as f64
+    }
+}
+
+/// Document frequency of every token, remembering the order tokens were first seen in.
+///
+/// Kept up to date with [`DocumentFrequencies::add`] as documents arrive, a running
+/// count lets long-running ingestion rebuild a `min_df`/`max_df` pruned vocabulary
+/// with [`Vocabulary::from_document_frequencies`] without rescanning history. Clone
+/// it to take a snapshot, or persist it with [`DocumentFrequencies::save`].
+///
+/// Serializes as a JSON object with the number of documents and the `[token, df]`
+/// pairs in first-seen order.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(into = "FrequencyTable", from = "FrequencyTable")]
+pub struct DocumentFrequencies {
+    order: Vec<String>,
+    counts: HashMap<String, usize>,
+    documents: usize,
+}
+
+#[derive(Serialize, Deserialize)]
+struct FrequencyTable {
+    documents: usize,
+    tokens: Vec<(String, usize)>,
+}
+
+impl From<DocumentFrequencies> for FrequencyTable {
+    fn from(mut frequencies: DocumentFrequencies) -> Self {
+        let tokens = frequencies
+            .order
+            .into_iter()
+            .map(|token| {
+                let count = frequencies.counts.remove(&token).unwrap_or(0);
+                (token, count)
+            })
+            .collect();
+        Self {
+            documents: frequencies.documents,
+            tokens,
+        }
+    }
+}
+
+impl From<FrequencyTable> for DocumentFrequencies {
+    fn from(table: FrequencyTable) -> Self {
+        Self {
+            order: table
+                .tokens
+                .iter()
+                .map(|(token, _)| token.clone())
+                .collect(),
+            counts: table.tokens.into_iter().collect(),
+            documents: table.documents,
+        }
+    }
+}
+
+impl DocumentFrequencies {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn from_dataset(dataset: &Dataset) -> Self {
+        let mut frequencies = Self::new();
+        frequencies.extend(dataset);
+        frequencies
+    }
+
+    /// Number of documents containing `token`.
+    pub fn get(&self, token: &str) -> usize {
+        self.counts.get(token).copied().unwrap_or(0)
+    }
+
+    /// Number of documents counted so far.
+    pub fn documents(&self) -> usize {
+        self.documents
+    }
+
+    /// Every token with its document frequency, in the order tokens were first seen.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, usize)> {
+        self.order
+            .iter()
+            .map(|token| (token.as_str(), self.counts[token]))
+    }
+
+    /// Counts every document of `dataset`.
+    pub fn extend(&mut self, dataset: &Dataset) {
+        for data in &dataset.data {
+            self.add(&data.tokens);
+        }
+    }
+
+    /// Counts one more document made of `tokens`.
+    pub fn add(&mut self, tokens: &[String]) {
+        self.documents += 1;
+        let mut seen = HashSet::new();
+        for token in tokens.iter().filter(|token| seen.insert(token.as_str())) {
+            match self.counts.get_mut(token) {
+                Some(count) => *count += 1,
+                None => {
+                    self.order.push(token.clone());
+                    self.counts.insert(token.clone(), 1);
+                }
+            }
+        }
+    }
+
+    /// Writes the counts as JSON, to resume counting later.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), std::io::Error> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        serde_json::to_writer(&mut writer, self)?;
+        writer.flush()
+    }
+
+    /// Reads counts written by [`DocumentFrequencies::save`].
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, std::io::Error> {
+        Ok(serde_json::from_reader(BufReader::new(File::open(path)?))?)
+    }
+}
+
+impl Dataset {
+    /// Number of documents containing each token, counted in a single pass.
+    pub fn document_frequencies(&self) -> HashMap<String, usize> {
+        DocumentFrequencies::from_dataset(self).counts
+    }
+}
+
+impl Vocabulary {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn from_dataset(dataset: &Dataset) -> Self {
+        let mut vocabulary = Self::new();
+        vocabulary.extend(dataset);
+        vocabulary
+    }
+
+    pub fn build(dataset: &Dataset, config: &VocabConfig) -> Self {
+        Self::from_document_frequencies(&DocumentFrequencies::from_dataset(dataset), config)
+    }
+
+    /// Like [`Vocabulary::build`] from counts gathered beforehand, e.g. incrementally.
+    pub fn from_document_frequencies(
+        frequencies: &DocumentFrequencies,
+        config: &VocabConfig,
+    ) -> Self {
+        let mut vocabulary = Self::new();
+        for token in &frequencies.order {
+            if config.accepts(token)
+                && config.accepts_frequency(frequencies.counts[token], frequencies.documents)
+            {
+                vocabulary.insert(token);
+            }
+        }
+        vocabulary
+    }
+
+    /// Appends the tokens of `dataset` that are not known yet at the end of the vocabulary.
+    pub fn extend(&mut self, dataset: &Dataset) {
+        for token in dataset.data.iter().flat_map(|data| data.tokens.iter()) {
+            self.insert(token);
+        }
+    }
+
+    /// Returns the index of `token`, assigning the next free one if it is new.
+    pub fn insert(&mut self, token: &str) -> usize {
+        if let Some(index) = self.index.get(token) {
+            return *index;
+        }
+        let index = self.index.len();
+        self.index.insert(token.to_string(), index);
+        index
+    }
+
+    pub fn get(&self, token: &str) -> Option<usize> {
+        self.index.get(token).copied()
+    }
+
+    pub fn contains(&self, token: &str) -> bool {
+        self.index.contains_key(token)
+    }
+
+    pub fn len(&self) -> usize {
+        self.index.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&str, usize)> {
+        self.index
+            .iter()
+            .map(|(token, index)| (token.as_str(), *index))
+    }
+
+    /// Writes the vocabulary as JSON, independently of any model trained on it.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), std::io::Error> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        serde_json::to_writer(&mut writer, self)?;
+        writer.flush()
+    }
+
+    /// Reads a vocabulary written by [`Vocabulary::save`]. Fails with
+    /// [`InvalidData`](std::io::ErrorKind::InvalidData) when the indices are not
+    /// `0..len`, each used once.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, std::io::Error> {
+        Ok(serde_json::from_reader(BufReader::new(File::open(path)?))?)
+    }
+}
+
+impl TryFrom<HashMap<String, usize>> for Vocabulary {
+    type Error = String;
+
+    fn try_from(index: HashMap<String, usize>) -> Result<Self, Self::Error> {
+        let mut used = vec![false; index.len()];
+        for (token, column) in &index {
+            match used.get_mut(*column) {
+                Some(true) => return Err(format!("vocabulary index {column} is used twice")),
+                Some(slot) => *slot = true,
+                None => {
+                    return Err(format!(
+                        "vocabulary index {column} of `{token}` is out of range for {} tokens",
+                        index.len()
+                    ))
+                }
+            }
+        }
+        Ok(Self { index })
+    }
+}
+
+impl Serialize for Vocabulary {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_map(self.index.iter().collect::<BTreeMap<_, _>>())
+    }
+}
+
+/// Jaccard similarity of the token sets of two datasets, from `0` for disjoint to
+/// `1` for identical vocabularies, a quick check for domain shift between corpora.
+/// Two datasets without any tokens count as identical.
+pub fn vocabulary_jaccard(a: &Dataset, b: &Dataset) -> f64 {
+    let tokens = |dataset: &Dataset| {
+        dataset
+            .data
+            .iter()
+            .flat_map(|data| data.tokens.iter().map(String::as_str))
+            .collect::<HashSet<_>>()
+    };
+    let (a, b) = (tokens(a), tokens(b));
+    let union = a.union(&b).count();
+    if union == 0 {
+        return 1.0;
+    }
+    a.intersection(&b).count() as f64 / union as f64
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashMap;
+
+    use crate::fixtures::dataset;
+    use crate::Label;
+
+    use super::{vocabulary_jaccard, DocumentFrequencies, VocabConfig, Vocabulary};
+
+    #[test]
+    fn test_extend_keeps_indices() {
+        let mut vocabulary = Vocabulary::from_dataset(&dataset(&[
+            (Label::Ham, "see you soon"),
+            (Label::Spam, "free prize"),
+        ]));
+        let before: Vec<_> = vocabulary
+            .iter()
+            .map(|(token, index)| (token.to_string(), index))
+            .collect();
+
+        vocabulary.extend(&dataset(&[(Label::Spam, "free cash prize now")]));
+
+        assert_eq!(vocabulary.len(), 7);
+        for (token, index) in before {
+            assert_eq!(vocabulary.get(&token), Some(index));
+        }
+        assert!(vocabulary.get("cash").unwrap() >= 5);
+        assert!(vocabulary.get("now").unwrap() >= 5);
+    }
+
+    #[test]
+    fn test_serialization_is_deterministic() {
+        let tokens = [
+            ("prize", 0),
+            ("free", 1),
+            ("call", 2),
+            ("now", 3),
+            ("win", 4),
+        ];
+        let first = Vocabulary {
+            index: tokens
+                .iter()
+                .map(|(token, index)| (token.to_string(), *index))
+                .collect(),
+        };
+        let mut index = HashMap::with_capacity(1024);
+        for (token, i) in tokens.iter().rev() {
+            index.insert(token.to_string(), *i);
+        }
+        let second = Vocabulary { index };
+
+        let json = serde_json::to_string(&first).expect("failed to serialize");
+        assert_eq!(
+            json,
+            serde_json::to_string(&second).expect("failed to serialize")
+        );
+        assert_eq!(json, r#"{"call":2,"free":1,"now":3,"prize":0,"win":4}"#);
+        assert_eq!(
+            serde_json::from_str::<Vocabulary>(&json).expect("failed to deserialize"),
+            first
+        );
+    }
+
+    #[test]
+    fn test_token_filter() {
+        let config = VocabConfig::default()
+            .with_token_filter(|token| !token.chars().all(|c| c.is_ascii_digit()));
+        let vocabulary = Vocabulary::build(
+            &dataset(&[(Label::Spam, "call 08712300 now"), (Label::Ham, "at 5 ok")]),
+            &config,
+        );
+        assert_eq!(vocabulary.len(), 4);
+        assert!(!vocabulary.contains("08712300"));
+        assert!(!vocabulary.contains("5"));
+        assert!(vocabulary.contains("call"));
+    }
+
+    #[test]
+    fn test_document_frequency_pruning() {
+        let dataset = dataset(&[
+            (Label::Spam, "free free prize now"),
+            (Label::Spam, "free cash now"),
+            (Label::Ham, "see you now"),
+            (Label::Ham, "call you later"),
+        ]);
+        let vocabulary = Vocabulary::build(
+            &dataset,
+            &VocabConfig::default().with_min_df(2).with_max_df(0.5),
+        );
+        assert_eq!(vocabulary.len(), 2);
+        assert_eq!(vocabulary.get("free"), Some(0));
+        assert_eq!(vocabulary.get("you"), Some(1));
+    }
+
+    #[test]
+    fn test_document_frequencies() {
+        let dataset = dataset(&[
+            (Label::Spam, "free free prize"),
+            (Label::Ham, "free lunch"),
+            (Label::Ham, "lunch later"),
+        ]);
+        let expected = [("free", 2), ("prize", 1), ("lunch", 2), ("later", 1)]
+            .into_iter()
+            .map(|(token, df)| (token.to_string(), df))
+            .collect::<HashMap<_, _>>();
+        assert_eq!(dataset.document_frequencies(), expected);
+    }
+
+    #[test]
+    fn test_incremental_document_frequencies() {
+        let batches = [
+            dataset(&[
+                (Label::Spam, "free free prize now"),
+                (Label::Ham, "see you now"),
+            ]),
+            dataset(&[(Label::Spam, "free cash now")]),
+            dataset(&[(Label::Ham, "call you later"), (Label::Ham, "see you")]),
+        ];
+        let mut incremental = DocumentFrequencies::new();
+        for batch in &batches {
+            incremental.extend(batch);
+        }
+        let snapshot = incremental.clone();
+        incremental.add(&["free".to_string()]);
+        assert_eq!(incremental.get("free"), 3);
+        assert_eq!(snapshot.get("free"), 2);
+
+        let all = dataset(&[
+            (Label::Spam, "free free prize now"),
+            (Label::Ham, "see you now"),
+            (Label::Spam, "free cash now"),
+            (Label::Ham, "call you later"),
+            (Label::Ham, "see you"),
+        ]);
+        assert_eq!(snapshot, DocumentFrequencies::from_dataset(&all));
+        assert_eq!(snapshot.documents(), 5);
+        assert_eq!(
+            snapshot
+                .iter()
+                .map(|(token, df)| (token.to_string(), df))
+                .collect::<HashMap<_, _>>(),
+            all.document_frequencies()
+        );
+        assert_eq!(
+            snapshot.iter().take(4).collect::<Vec<_>>(),
+            vec![("free", 2), ("prize", 1), ("now", 3), ("see", 2)]
+        );
+
+        let config = VocabConfig::default().with_min_df(2).with_max_df(0.6);
+        assert_eq!(
+            Vocabulary::from_document_frequencies(&snapshot, &config),
+            Vocabulary::build(&all, &config)
+        );
+
+        let json = serde_json::to_string(&snapshot).expect("failed to serialize");
+        assert!(json.starts_with(r#"{"documents":5,"tokens":[["free",2],["prize",1]"#));
+        assert_eq!(
+            serde_json::from_str::<DocumentFrequencies>(&json).expect("failed to deserialize"),
+            snapshot
+        );
+    }
+
+    #[test]
+    fn test_vocabulary_jaccard() {
+        let a = dataset(&[(Label::Spam, "free prize"), (Label::Ham, "free lunch")]);
+        let b = dataset(&[(Label::Ham, "lunch at home"), (Label::Spam, "free lunch")]);
+        assert_eq!(vocabulary_jaccard(&a, &b), 2.0 / 5.0);
+        assert_eq!(vocabulary_jaccard(&a, &a), 1.0);
+        assert_eq!(
+            vocabulary_jaccard(&a, &dataset(&[(Label::Ham, "see you")])),
+            0.0
+        );
+    }
+
+    #[test]
+    fn test_save_load() {
+        let vocabulary = Vocabulary::from_dataset(
+            &crate::preprocess_file("../../SMSSpamCollection").expect("failed to load"),
+        );
+        let path = std::env::temp_dir().join("sms-data-clean-vocabulary.json");
+
+        vocabulary.save(&path).expect("failed to save");
+        let loaded = Vocabulary::load(&path).expect("failed to load");
+        assert!(loaded.len() > 1000);
+        assert_eq!(loaded, vocabulary);
+    }
+
+    #[test]
+    fn test_load_invalid_indices() {
+        let path = std::env::temp_dir().join("sms-data-clean-invalid-vocabulary.json");
+        for (json, message) in [
+            (r#"{"free":0,"prize":2}"#, "out of range"),
+            (r#"{"free":0,"prize":1,"win":1}"#, "used twice"),
+        ] {
+            std::fs::write(&path, json).unwrap();
+            let err = Vocabulary::load(&path).unwrap_err();
+            assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+            assert!(err.to_string().contains(message), "{}", err);
+        }
+    }
+}
@@ -0,0 +1,88 @@
+use std::io::Write;
+use std::path::PathBuf;
+use std::process::{Command, Stdio};
+use std::sync::OnceLock;
+
+const DATASET: &str = "../../SMSSpamCollection";
+
+/// The model file written by `train`, trained once for all the tests.
+fn model() -> &'static str {
+    static MODEL: OnceLock<PathBuf> = OnceLock::new();
+    MODEL
+        .get_or_init(|| {
+            let dir = std::env::temp_dir().join("sms-data-clean-cli");
+            std::fs::create_dir_all(&dir).expect("failed to create temp dir");
+            let model = dir.join("model.json");
+            let output = Command::new(env!("CARGO_BIN_EXE_sms-data-clean"))
+                .args(["train", "--dataset", DATASET, "--model"])
+                .arg(&model)
+                .output()
+                .expect("failed to run");
+            assert!(output.status.success(), "{:?}", output);
+            model
+        })
+        .to_str()
+        .expect("temp dir is not UTF-8")
+}
+
+#[test]
+fn test_classify_spam_message() {
+    let output = Command::new(env!("CARGO_BIN_EXE_sms-data-clean"))
+        .args(["classify", "--model", model(), "--top", "3"])
+        .arg("WINNER!! You have won a free prize, call now to claim your cash")
+        .output()
+        .expect("failed to run");
+    assert!(output.status.success(), "{:?}", output);
+
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let lines = stdout.lines().collect::<Vec<_>>();
+    assert_eq!(lines[0], "verdict: spam");
+    let probability = lines[1]
+        .strip_prefix("spam_probability: ")
+        .and_then(|probability| probability.parse::<f64>().ok())
+        .expect("missing probability");
+    assert!(probability > 0.5);
+    assert_eq!(lines[2], "top tokens:");
+    assert_eq!(lines.len(), 6);
+}
+
+#[test]
+fn test_classify_from_stdin() {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_sms-data-clean"))
+        .args(["classify", "--model", model()])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .expect("failed to run");
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(b"Are we still meeting for lunch tomorrow?\n")
+        .unwrap();
+    let output = child.wait_with_output().expect("failed to run");
+    assert!(output.status.success(), "{:?}", output);
+
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.starts_with("verdict: ham\n"), "{}", stdout);
+}
+
+#[test]
+fn test_missing_model() {
+    let output = Command::new(env!("CARGO_BIN_EXE_sms-data-clean"))
+        .args(["classify", "--model", "no-such-model.json", "free prize"])
+        .output()
+        .expect("failed to run");
+    assert_eq!(output.status.code(), Some(1));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("failed to load the model"));
+}
+
+#[test]
+fn test_usage() {
+    let output = Command::new(env!("CARGO_BIN_EXE_sms-data-clean"))
+        .arg("evaluate")
+        .output()
+        .expect("failed to run");
+    assert_eq!(output.status.code(), Some(2));
+    assert!(String::from_utf8_lossy(&output.stderr).starts_with("usage:"));
+}
@@ -1,34 +1,762 @@
-use fluvio_smartmodule::{smartmodule, Record, RecordData, Result};
-use smartcore::linalg::basic::{arrays::Array2, matrix::DenseMatrix};
-use sms_data_clean::bag_of_words;
+//! Map smartmodule adding the spam verdict of a message.
+//!
+//! Init parameters:
+//! - `non_english`: `score` (default) classifies every message, `skip` leaves the
+//!   verdict of messages detected as non-English `null`, since the model is
+//!   trained on English only.
+//! - `empty_after_cleaning`: `score` (default) classifies every message, `flag`
+//!   leaves messages without a single token after preprocessing, e.g. made only of
+//!   stop words, unscored: `label` is `unknown` and `"empty_after_cleaning": true`
+//!   is added, since their verdict would rest on the class priors alone.
+//! - `max_chars`: only the first `max_chars` characters of a message are
+//!   classified, bounding the cost of pathological records. Unlimited by default.
+//! - `max_tokens`: only the first `max_tokens` tokens of a message are looked up
+//!   in the vocabulary, bounding memory on records made of a huge number of tiny
+//!   tokens. Unlimited by default.
+//! - `field`: when set, record values are parsed as JSON objects and the string
+//!   under this key is classified. The verdict is added to the object, keeping
+//!   its other fields, e.g. an id or timestamp. When unset, or for values that
+//!   are not JSON objects, the whole value is the message and the output is a new
+//!   `{"sms": ..}` object. Objects that already have one of the output fields, e.g.
+//!   `label`, are rejected rather than overwritten.
+//! - `fields`: comma separated `name:weight` pairs, e.g. `subject:3,body:1`, to
+//!   classify JSON object records made of several text fields. Every token of a
+//!   field counts `weight` times, one when the weight is omitted. The model must be
+//!   trained on records weighted the same way. Can't be combined with `field`.
+//! - `blocklist`, `allowlist`: comma separated keywords, e.g. URLs, overriding the
+//!   model. Messages containing a blocklist keyword are spam, messages containing
+//!   an allowlist keyword are not. `decided_by` in the output tells whether a rule
+//!   or the model decided. Rules match the raw text, so they also decide messages
+//!   that `non_english=skip` leaves unscored by the model.
+//! - `min_coverage`: fraction between 0 and 1. When less than this fraction of a
+//!   message's tokens is in the vocabulary, the model abstains: `label` is
+//!   `unknown` and `spam` is `null`, instead of a confident guess based on the few
+//!   known tokens. Unset by default, so the model always answers.
+//! - `spam_prior`: fraction strictly between 0 and 1 of the messages expected to be
+//!   spam in this deployment, replacing the base rate of the training corpus.
+//!   Defaults to the training base rate.
+//! - `precision`: number of decimal places, at most 15, `spam_probability` is
+//!   rounded to in the output, keeping records compact. Full precision by default.
+//! - `positive_label`, `negative_label`: names of the two classes in the output,
+//!   `spam` and `ham` by default, to reuse the module on other binary tasks, e.g.
+//!   `positive` and `negative` for sentiment. The verdict field and the probability
+//!   field are named after the positive label, e.g. `positive` and
+//!   `positive_probability`.
+//!
+//! Scored messages get a `label`, the positive or the negative label, next to the
+//! verdict and probability fields named after the positive label, `spam` and
+//! `spam_probability` by default. Messages the model abstains on, see
+//! `empty_after_cleaning` and `min_coverage`, get a `label` of `unknown`, so
+//! neither class can be named `unknown`.
+use std::sync::{OnceLock, RwLock};
 
-mod model {
-    include!(concat!(env!("OUT_DIR"), "/model.rs"));
+use fluvio_smartmodule::dataplane::smartmodule::SmartModuleExtraParams;
+use fluvio_smartmodule::{eyre, smartmodule, Record, RecordData, Result};
+use serde_json::Value;
+use sms_data_clean::{
+    detect_language, truncate_chars, Classifier, FieldWeights, KeywordRules, Tokenizer,
+};
+use spam_model as model;
+
+#[derive(Debug, Default)]
+struct Config {
+    skip_non_english: bool,
+    flag_empty: bool,
+    max_chars: Option<usize>,
+    max_tokens: Option<usize>,
+    field: Option<String>,
+    fields: Option<FieldWeights>,
+    rules: KeywordRules,
+    min_coverage: Option<f64>,
+    spam_prior: Option<f64>,
+    precision: Option<i32>,
+    positive_label: Option<String>,
+    negative_label: Option<String>,
+    /// The bundled classifier with the overrides above, built once on first use.
+    classifier: OnceLock<Classifier>,
+}
+
+impl Config {
+    fn positive_label(&self) -> &str {
+        self.positive_label.as_deref().unwrap_or("spam")
+    }
+
+    fn negative_label(&self) -> &str {
+        self.negative_label.as_deref().unwrap_or("ham")
+    }
+
+    /// The classifier every record is scored with, see [`build_classifier`].
+    fn classifier(&self) -> Result<&Classifier> {
+        if let Some(classifier) = self.classifier.get() {
+            return Ok(classifier);
+        }
+        let classifier = build_classifier(self)?;
+        Ok(self.classifier.get_or_init(|| classifier))
+    }
+}
+
+/// Set once by `init`, the defaults until then.
+static CONFIG: RwLock<Option<Config>> = RwLock::new(None);
+
+/// The config records are scored with before `init`, kept so that its classifier
+/// is built only once.
+static DEFAULT_CONFIG: OnceLock<Config> = OnceLock::new();
+
+#[smartmodule(init)]
+fn init(params: SmartModuleExtraParams) -> Result<()> {
+    let config = parse_config(&params)?;
+    // Built here rather than on the first record.
+    config.classifier()?;
+    let mut current = CONFIG.write().map_err(|_| eyre!("config lock poisoned"))?;
+    if current.is_some() {
+        return Err(eyre!("config already initialized"));
+    }
+    *current = Some(config);
+    Ok(())
+}
+
+/// Forgets the config set by `init`, so tests can initialize the module again.
+#[cfg(test)]
+fn reset_config() {
+    *CONFIG.write().unwrap() = None;
+}
+
+fn parse_config(params: &SmartModuleExtraParams) -> Result<Config> {
+    let skip_non_english = match params.get("non_english").map(String::as_str) {
+        None | Some("score") => false,
+        Some("skip") => true,
+        Some(other) => {
+            return Err(eyre!(
+                "invalid non_english `{}`, expected `score` or `skip`",
+                other
+            ))
+        }
+    };
+    let flag_empty = match params.get("empty_after_cleaning").map(String::as_str) {
+        None | Some("score") => false,
+        Some("flag") => true,
+        Some(other) => {
+            return Err(eyre!(
+                "invalid empty_after_cleaning `{}`, expected `score` or `flag`",
+                other
+            ))
+        }
+    };
+    let max_chars = params
+        .get("max_chars")
+        .map(|max_chars| max_chars.parse())
+        .transpose()?;
+    let max_tokens = params
+        .get("max_tokens")
+        .map(|max_tokens| max_tokens.parse())
+        .transpose()?;
+    let min_coverage = params
+        .get("min_coverage")
+        .map(|min_coverage| min_coverage.parse::<f64>())
+        .transpose()?;
+    if let Some(min_coverage) = min_coverage.filter(|min| !(0.0..=1.0).contains(min)) {
+        return Err(eyre!(
+            "invalid min_coverage `{}`, expected a fraction between 0 and 1",
+            min_coverage
+        ));
+    }
+    let spam_prior = params
+        .get("spam_prior")
+        .map(|spam_prior| spam_prior.parse::<f64>())
+        .transpose()?;
+    if let Some(spam_prior) = spam_prior.filter(|prior| !(*prior > 0.0 && *prior < 1.0)) {
+        return Err(eyre!(
+            "invalid spam_prior `{}`, expected a fraction strictly between 0 and 1",
+            spam_prior
+        ));
+    }
+    let precision = params
+        .get("precision")
+        .map(|precision| precision.parse::<i32>())
+        .transpose()?;
+    if let Some(precision) = precision.filter(|places| !(0..=MAX_PRECISION).contains(places)) {
+        return Err(eyre!(
+            "invalid precision `{}`, expected between 0 and {} decimal places",
+            precision,
+            MAX_PRECISION
+        ));
+    }
+    let positive_label = params.get("positive_label").cloned();
+    let negative_label = params.get("negative_label").cloned();
+    for label in positive_label.iter().chain(&negative_label) {
+        if label.is_empty() || RESERVED_FIELDS.contains(&label.as_str()) {
+            return Err(eyre!("invalid label name `{}`", label));
+        }
+    }
+    let fields = params
+        .get("fields")
+        .map(|fields| FieldWeights::parse(fields))
+        .transpose()?;
+    if fields.is_some() && params.get("field").is_some() {
+        return Err(eyre!("`field` and `fields` can't be set together"));
+    }
+    let keywords = |name: &str| {
+        params
+            .get(name)
+            .map(|keywords| {
+                keywords
+                    .split(',')
+                    .map(str::trim)
+                    .filter(|keyword| !keyword.is_empty())
+                    .map(ToString::to_string)
+                    .collect()
+            })
+            .unwrap_or_default()
+    };
+    let config = Config {
+        skip_non_english,
+        flag_empty,
+        max_chars,
+        max_tokens,
+        field: params.get("field").cloned(),
+        fields,
+        rules: KeywordRules {
+            blocklist: keywords("blocklist"),
+            allowlist: keywords("allowlist"),
+        },
+        min_coverage,
+        spam_prior,
+        precision,
+        positive_label,
+        negative_label,
+        classifier: OnceLock::new(),
+    };
+    if config.positive_label() == config.negative_label() {
+        return Err(eyre!(
+            "positive and negative label are both `{}`",
+            config.positive_label()
+        ));
+    }
+    Ok(config)
+}
+
+/// Names a label can't take: the output fields it would replace, and `unknown`, the
+/// label of messages the model abstains on.
+const RESERVED_FIELDS: &[&str] = &[
+    "sms",
+    "language",
+    "label",
+    "decided_by",
+    "empty_after_cleaning",
+    "unknown",
+];
+
+/// Decimal places beyond which an `f64` carries no more information.
+const MAX_PRECISION: i32 = 15;
+
+/// `value` rounded to `places` decimal places.
+fn round(value: f64, places: i32) -> f64 {
+    let scale = 10f64.powi(places);
+    (value * scale).round() / scale
 }
+
 #[smartmodule(map)]
 pub fn map(record: &Record) -> Result<(Option<RecordData>, RecordData)> {
     let key = record.key.clone();
-    let sms = std::str::from_utf8(record.value.as_ref())?;
-
-    let tokens = std::str::from_utf8(record.value.as_ref())?
-        .chars()
-        .filter(|c| !c.is_ascii_punctuation())
-        .collect::<String>()
-        .split_ascii_whitespace()
-        .map(ToString::to_string)
-        .collect::<Vec<String>>();
-
-    let x = bag_of_words::<usize>(tokens, &model::vocabulary());
-    let x = DenseMatrix::from_row(&x);
-    let model = model::naive_bayes_model();
-    let y = model.predict(&x)?;
-    let spam = y[0] == 1;
-
-    let value = serde_json::json!({
-        "sms": sms,
-        "spam": spam,
-    })
-    .to_string();
-
-    Ok((key, value.into()))
+    let config = CONFIG.read().map_err(|_| eyre!("config lock poisoned"))?;
+    let value = match config.as_ref() {
+        Some(config) => classify(config, record.value.as_ref())?,
+        None => classify(
+            DEFAULT_CONFIG.get_or_init(Config::default),
+            record.value.as_ref(),
+        )?,
+    };
+
+    Ok((key, value.to_string().into()))
+}
+
+/// Output object for a record value.
+fn classify(config: &Config, value: &[u8]) -> Result<Value> {
+    if let Some(fields) = &config.fields {
+        let mut object = serde_json::from_slice::<Value>(value)
+            .ok()
+            .filter(Value::is_object)
+            .ok_or_else(|| eyre!("record is not a JSON object"))?;
+        check_output_fields(config, &object)?;
+        let record = object.clone();
+        let text = fields.text(&record);
+        annotate(config, &mut object, &text, Some(&record))?;
+        return Ok(object);
+    }
+
+    let object = config.field.as_ref().and_then(|field| {
+        serde_json::from_slice::<Value>(value)
+            .ok()
+            .filter(Value::is_object)
+            .map(|object| (field, object))
+    });
+    match object {
+        Some((field, mut object)) => {
+            let text = object
+                .get(field)
+                .and_then(Value::as_str)
+                .ok_or_else(|| eyre!("record has no string field `{}`", field))?
+                .to_string();
+            check_output_fields(config, &object)?;
+            annotate(config, &mut object, &text, None)?;
+            Ok(object)
+        }
+        None => {
+            let sms = std::str::from_utf8(value)?;
+            let mut object = serde_json::json!({ "sms": sms });
+            annotate(config, &mut object, sms, None)?;
+            Ok(object)
+        }
+    }
+}
+
+/// Fails when `object` already has a field the verdict would overwrite.
+fn check_output_fields(config: &Config, object: &Value) -> Result<()> {
+    let positive = config.positive_label();
+    let probability = format!("{}_probability", positive);
+    let fields = [
+        "language",
+        "label",
+        "decided_by",
+        "empty_after_cleaning",
+        positive,
+        probability.as_str(),
+    ];
+    match fields.iter().find(|field| object.get(**field).is_some()) {
+        Some(field) => Err(eyre!(
+            "record already has a `{}` field, which the verdict would overwrite",
+            field
+        )),
+        None => Ok(()),
+    }
+}
+
+/// The bundled classifier with the configured overrides.
+fn build_classifier(config: &Config) -> Result<Classifier> {
+    let mut classifier = model::classifier();
+    let mut tokenizer = Tokenizer::standard();
+    if let Some(max_chars) = config.max_chars {
+        tokenizer = tokenizer.with_max_chars(max_chars);
+    }
+    if let Some(max_tokens) = config.max_tokens {
+        tokenizer = tokenizer.with_max_tokens(max_tokens);
+    }
+    classifier = classifier.with_tokenizer(tokenizer);
+    if let Some(spam_prior) = config.spam_prior {
+        classifier = classifier.with_spam_prior(spam_prior)?;
+    }
+    Ok(classifier)
+}
+
+/// Adds the language and verdict of `text` to `object`, scoring the weighted
+/// `fields` of `record` instead of `text` with the model when given.
+fn annotate(config: &Config, object: &mut Value, text: &str, record: Option<&Value>) -> Result<()> {
+    let text = match config.max_chars {
+        Some(max_chars) => truncate_chars(text, max_chars),
+        None => text,
+    };
+
+    let positive = config.positive_label();
+    let language = detect_language(text);
+    let english = language.is_none_or(|language| language == "eng");
+
+    object["language"] = language.into();
+    let prediction = match config.rules.decide(text) {
+        Some(prediction) => Some(prediction),
+        None if !english && config.skip_non_english => {
+            object[positive] = Value::Null;
+            return Ok(());
+        }
+        None => {
+            let classifier = config.classifier()?;
+            // Tokenized once, the checks below and the prediction share the tokens. The
+            // coverage counts every token once whatever the weight of its field.
+            let tokens = match (record, &config.fields) {
+                (Some(record), Some(fields)) => fields.tokenize(classifier.tokenizer(), record),
+                _ => classifier
+                    .tokenize(text)
+                    .into_iter()
+                    .map(|token| (token, 1))
+                    .collect(),
+            };
+            let covered = config.min_coverage.is_none_or(|min_coverage| {
+                let words = tokens.iter().map(|(token, _)| token).collect::<Vec<_>>();
+                classifier.token_coverage(&words) >= min_coverage
+            });
+            if config.flag_empty && tokens.is_empty() {
+                object["empty_after_cleaning"] = true.into();
+                None
+            } else if !covered {
+                None
+            } else {
+                Some(classifier.predict_features(&classifier.weighted_token_features(&tokens)))
+            }
+        }
+    };
+    match prediction {
+        Some(prediction) => {
+            object["label"] = if prediction.spam {
+                config.positive_label()
+            } else {
+                config.negative_label()
+            }
+            .into();
+            object[positive] = prediction.spam.into();
+            object[format!("{}_probability", positive)] = match config.precision {
+                Some(places) => round(prediction.spam_probability, places),
+                None => prediction.spam_probability,
+            }
+            .into();
+            object["decided_by"] = prediction.decided_by.to_string().into();
+        }
+        None => {
+            object["label"] = "unknown".into();
+            object[positive] = Value::Null;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::BTreeMap;
+    use std::time::Instant;
+
+    use fluvio_smartmodule::dataplane::smartmodule::SmartModuleExtraParams;
+    use fluvio_smartmodule::Record;
+    use serde_json::Value;
+    use sms_data_clean::{FieldWeights, KeywordRules};
+
+    use super::{classify, init, map, model, parse_config, reset_config, Config};
+
+    fn params(params: &[(&str, &str)]) -> SmartModuleExtraParams {
+        params
+            .iter()
+            .map(|(key, value)| (key.to_string(), value.to_string()))
+            .collect::<BTreeMap<_, _>>()
+            .into()
+    }
+
+    fn map_value(sms: &str) -> Value {
+        let (_, value) = map(&Record::new(sms)).expect("failed to map");
+        serde_json::from_slice(value.as_ref()).expect("invalid output")
+    }
+
+    // The only test touching the global config, so that parallel tests can't see
+    // each other's settings.
+    #[test]
+    fn test_reinitialize() {
+        let sms = "see you at lunch http://bad.example.com";
+        assert_eq!(map_value(sms)["decided_by"], "model");
+
+        init(params(&[("blocklist", "http://bad.example.com")])).expect("failed to init");
+        assert!(init(params(&[])).is_err());
+        let value = map_value(sms);
+        assert_eq!(value["spam"], true);
+        assert_eq!(value["decided_by"], "blocklist");
+
+        reset_config();
+        init(params(&[("min_coverage", "0.9")])).expect("failed to init");
+        let value = map_value("free zorblax quuxify snargle");
+        assert_eq!(value["label"], "unknown");
+        assert_eq!(map_value(sms)["decided_by"], "model");
+
+        reset_config();
+        assert!(init(params(&[("min_coverage", "2")])).is_err());
+        assert_ne!(
+            map_value("free zorblax quuxify snargle")["label"],
+            "unknown"
+        );
+    }
+
+    #[test]
+    fn test_generated_model_loads() {
+        let model = model::naive_bayes_model();
+        assert_eq!(model.n_features(), model::vocabulary().len());
+
+        let classifier = model::classifier();
+        assert!(
+            classifier
+                .predict("free entry win cash prize")
+                .unwrap()
+                .spam
+        );
+        assert!(
+            !classifier
+                .predict("see you at lunch tomorrow")
+                .unwrap()
+                .spam
+        );
+    }
+
+    /// Per-record latency of rebuilding the classifier for every record, as before
+    /// it was kept in the config, against reusing it. Run with
+    /// `cargo test --release -- --ignored --nocapture test_classifier_reuse_latency`.
+    #[test]
+    #[ignore = "benchmark"]
+    fn test_classifier_reuse_latency() {
+        const RECORDS: u32 = 200;
+        let record = b"free entry win cash prize";
+
+        let start = Instant::now();
+        for _ in 0..RECORDS {
+            classify(&Config::default(), record).expect("failed to classify");
+        }
+        let rebuilt = start.elapsed() / RECORDS;
+
+        let config = Config::default();
+        config.classifier().expect("failed to build the classifier");
+        let start = Instant::now();
+        for _ in 0..RECORDS {
+            classify(&config, record).expect("failed to classify");
+        }
+        let reused = start.elapsed() / RECORDS;
+
+        println!(
+            "per record: {:?} rebuilding the classifier, {:?} reusing it",
+            rebuilt, reused
+        );
+        assert!(reused < rebuilt);
+    }
+
+    #[test]
+    fn test_classify_json_field() {
+        let config = Config {
+            field: Some("message".to_string()),
+            ..Default::default()
+        };
+        let value = classify(
+            &config,
+            br#"{"id": 7, "message": "free entry win cash prize"}"#,
+        )
+        .expect("failed to classify");
+
+        assert_eq!(value["id"], 7);
+        assert_eq!(value["message"], "free entry win cash prize");
+        assert_eq!(value["spam"], true);
+        assert!(value.get("sms").is_none());
+
+        assert!(classify(&config, br#"{"id": 7}"#).is_err());
+    }
+
+    #[test]
+    fn test_classify_json_field_collision() {
+        let config = Config {
+            field: Some("message".to_string()),
+            ..Default::default()
+        };
+        let err = classify(
+            &config,
+            br#"{"label": "urgent", "message": "free entry win cash prize"}"#,
+        )
+        .unwrap_err();
+        assert!(err.to_string().contains("`label`"), "{}", err);
+        assert!(classify(&config, br#"{"spam": 1, "message": "see you at lunch"}"#).is_err());
+
+        let config = Config {
+            fields: Some(FieldWeights::parse("subject,body").unwrap()),
+            ..Default::default()
+        };
+        assert!(classify(
+            &config,
+            br#"{"label": "urgent", "subject": "hi", "body": "see you"}"#
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_classify_non_json_with_field() {
+        let config = Config {
+            field: Some("message".to_string()),
+            ..Default::default()
+        };
+        let value = classify(&config, b"free entry win cash prize").expect("failed to classify");
+
+        assert_eq!(value["sms"], "free entry win cash prize");
+        assert_eq!(value["spam"], true);
+
+        let value = classify(&config, b"42").expect("failed to classify");
+        assert_eq!(value["sms"], "42");
+    }
+
+    #[test]
+    fn test_classify_weighted_fields() {
+        let record =
+            br#"{"id": 7, "subject": "free entry win cash prize", "body": "see you at lunch tomorrow"}"#;
+        let config = |spec: &str| Config {
+            fields: Some(FieldWeights::parse(spec).unwrap()),
+            ..Default::default()
+        };
+
+        let value = classify(&config("subject:5,body:1"), record).expect("failed to classify");
+        assert_eq!(value["id"], 7);
+        assert_eq!(value["spam"], true);
+        let value = classify(&config("subject:1,body:10"), record).expect("failed to classify");
+        assert_eq!(value["spam"], false);
+
+        assert!(classify(&config("subject"), b"free entry win cash prize").is_err());
+        assert!(parse_config(&params(&[("fields", "subject:2"), ("field", "body")])).is_err());
+    }
+
+    #[test]
+    fn test_keyword_rules() {
+        let config = Config {
+            rules: KeywordRules {
+                blocklist: vec!["http://bad.example.com".to_string()],
+                allowlist: vec!["your library account".to_string()],
+            },
+            ..Default::default()
+        };
+
+        let value = classify(&config, b"see you at lunch http://bad.example.com")
+            .expect("failed to classify");
+        assert_eq!(value["spam"], true);
+        assert_eq!(value["decided_by"], "blocklist");
+
+        let value = classify(
+            &config,
+            b"free entry win cash prize for your library account",
+        )
+        .expect("failed to classify");
+        assert_eq!(value["spam"], false);
+        assert_eq!(value["decided_by"], "allowlist");
+
+        let value = classify(&config, b"free entry win cash prize").expect("failed to classify");
+        assert_eq!(value["spam"], true);
+        assert_eq!(value["decided_by"], "model");
+
+        let config = Config {
+            skip_non_english: true,
+            ..config
+        };
+        let french = "Bonjour, je serai en retard ce soir, ne m'attends pas pour le dîner";
+        let value = classify(&config, french.as_bytes()).expect("failed to classify");
+        assert_eq!(value["language"], "fra");
+        assert!(value["spam"].is_null());
+        let value = classify(
+            &config,
+            format!("{} http://bad.example.com", french).as_bytes(),
+        )
+        .expect("failed to classify");
+        assert_eq!(value["spam"], true);
+        assert_eq!(value["decided_by"], "blocklist");
+    }
+
+    #[test]
+    fn test_max_tokens() {
+        let config = Config {
+            max_tokens: Some(5),
+            ..Default::default()
+        };
+        let sms = format!("free entry win cash prize {}", "x ".repeat(100_000));
+        let value = classify(&config, sms.as_bytes()).expect("failed to classify");
+        assert_eq!(value["spam"], true);
+    }
+
+    #[test]
+    fn test_spam_prior() {
+        let messages: [&[u8]; 3] = [b"see you at lunch", b"call me later", b"ok thanks"];
+        let spam_count = |config: &Config| {
+            messages
+                .iter()
+                .filter(|sms| classify(config, sms).expect("failed to classify")["spam"] == true)
+                .count()
+        };
+
+        let high = Config {
+            spam_prior: Some(0.999),
+            ..Default::default()
+        };
+        assert!(spam_count(&high) > spam_count(&Config::default()));
+    }
+
+    #[test]
+    fn test_probability_precision() {
+        let sms = b"call me about the free prize";
+        let full = classify(&Config::default(), sms).expect("failed to classify")
+            ["spam_probability"]
+            .as_f64()
+            .unwrap();
+
+        let config = Config {
+            precision: Some(3),
+            ..Default::default()
+        };
+        let value = classify(&config, sms).expect("failed to classify");
+        let output = value["spam_probability"].to_string();
+        let decimals = output
+            .split_once('.')
+            .map_or(0, |(_, decimals)| decimals.len());
+        assert!(decimals <= 3, "{}", output);
+        let rounded = value["spam_probability"].as_f64().unwrap();
+        assert_eq!(rounded, (full * 1000.0).round() / 1000.0);
+
+        assert!(parse_config(&params(&[("precision", "2")])).is_ok());
+        assert!(parse_config(&params(&[("precision", "16")])).is_err());
+        assert!(parse_config(&params(&[("precision", "-1")])).is_err());
+    }
+
+    #[test]
+    fn test_positive_label() {
+        let config = parse_config(&params(&[
+            ("positive_label", "positive"),
+            ("negative_label", "negative"),
+        ]))
+        .expect("failed to parse");
+
+        let value = classify(&config, b"free entry win cash prize").expect("failed to classify");
+        assert_eq!(value["positive"], true);
+        assert_eq!(value["label"], "positive");
+        assert!(value["positive_probability"].as_f64().unwrap() > 0.5);
+        assert!(value.get("spam").is_none());
+        assert!(value.get("spam_probability").is_none());
+
+        let value = classify(&config, b"see you at lunch tomorrow").expect("failed to classify");
+        assert_eq!(value["positive"], false);
+        assert_eq!(value["label"], "negative");
+
+        assert!(parse_config(&params(&[("positive_label", "ham")])).is_err());
+        assert!(parse_config(&params(&[("positive_label", "label")])).is_err());
+        assert!(parse_config(&params(&[("negative_label", "unknown")])).is_err());
+        assert!(parse_config(&params(&[("negative_label", "")])).is_err());
+    }
+
+    #[test]
+    fn test_flag_empty_after_cleaning() {
+        let config =
+            parse_config(&params(&[("empty_after_cleaning", "flag")])).expect("failed to parse");
+
+        let value = classify(&config, b"You are there?").expect("failed to classify");
+        assert_eq!(value["empty_after_cleaning"], true);
+        assert_eq!(value["label"], "unknown");
+        assert!(value["spam"].is_null());
+        assert!(value.get("spam_probability").is_none());
+
+        let value = classify(&config, b"free entry win cash prize").expect("failed to classify");
+        assert!(value.get("empty_after_cleaning").is_none());
+        assert_eq!(value["spam"], true);
+
+        let value = classify(&Config::default(), b"You are there?").expect("failed to classify");
+        assert!(value.get("empty_after_cleaning").is_none());
+        assert!(value["spam"].is_boolean());
+
+        assert!(parse_config(&params(&[("empty_after_cleaning", "drop")])).is_err());
+    }
+
+    #[test]
+    fn test_abstain_on_low_coverage() {
+        let config = Config {
+            min_coverage: Some(0.5),
+            ..Default::default()
+        };
+
+        let value = classify(&config, b"free zorblax quuxify snargle").expect("failed to classify");
+        assert_eq!(value["label"], "unknown");
+        assert!(value["spam"].is_null());
+        assert!(value.get("spam_probability").is_none());
+
+        let value = classify(&config, b"free entry win cash prize").expect("failed to classify");
+        assert_eq!(value["label"], "spam");
+        assert_eq!(value["spam"], true);
+    }
 }
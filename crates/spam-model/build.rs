@@ -0,0 +1,21 @@
+use sms_data_clean::codegen::{
+    check_accuracy, generate_model_module, split_seed, train_with_seed, MIN_ACCURACY,
+    SPLIT_SEED_VAR,
+};
+
+use std::env;
+use std::path::Path;
+fn main() {
+    println!("cargo:rerun-if-changed=build.rs");
+    println!("cargo:rerun-if-changed=../../SMSSpamCollection");
+    println!("cargo:rerun-if-env-changed={}", SPLIT_SEED_VAR);
+
+    let out_dir = env::var("OUT_DIR").unwrap();
+    let seed =
+        split_seed(env::var_os(SPLIT_SEED_VAR).as_deref()).unwrap_or_else(|err| panic!("{}", err));
+    let trained = train_with_seed("../../SMSSpamCollection", seed).expect("failed to init");
+    check_accuracy(&trained, MIN_ACCURACY).unwrap_or_else(|err| panic!("{}", err));
+
+    let dest_path = Path::new(&out_dir).join("model.rs");
+    generate_model_module(&trained, dest_path).expect("Failed to generate code");
+}
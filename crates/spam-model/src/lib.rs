@@ -0,0 +1,28 @@
+//! Model shared by the `spam-classifier` and `spam-rate` smartmodules.
+//!
+//! The build script trains it once on `SMSSpamCollection` and generates this
+//! module with `sms_data_clean::codegen`, so both smartmodules classify with the
+//! same weights.
+include!(concat!(env!("OUT_DIR"), "/model.rs"));
+
+#[cfg(test)]
+mod test {
+    use super::{classifier, naive_bayes_model, vocabulary};
+
+    #[test]
+    fn test_generated_module() {
+        assert_eq!(naive_bayes_model().n_features(), vocabulary().len());
+        let classifier = classifier();
+        let spam = classifier
+            .predict("WINNER!! Claim your free cash prize, call now")
+            .unwrap();
+        assert!(spam.spam);
+        assert!(spam.spam_probability > 0.5);
+        assert!(
+            !classifier
+                .predict("see you at lunch tomorrow")
+                .unwrap()
+                .spam
+        );
+    }
+}
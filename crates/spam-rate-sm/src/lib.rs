@@ -0,0 +1,78 @@
+//! Aggregate smartmodule reporting the spam rate of a topic over tumbling windows.
+//!
+//! Every record is classified with the same model as the `spam-classifier`
+//! smartmodule. The window length, in records, is read from the `window` init
+//! parameter and defaults to 100:
+//!
+//! ```text
+//! fluvio consume sms --aggregate spam-rate -e window=500
+//! ```
+//!
+//! Each output record carries the counts of the window in progress and the
+//! `spam_rate` of the last completed window.
+use std::sync::OnceLock;
+
+use fluvio_smartmodule::dataplane::smartmodule::SmartModuleExtraParams;
+use fluvio_smartmodule::{eyre, smartmodule, Record, RecordData, Result};
+use serde::{Deserialize, Serialize};
+use sms_data_clean::Classifier;
+use spam_model as model;
+
+const DEFAULT_WINDOW: usize = 100;
+
+static WINDOW: OnceLock<usize> = OnceLock::new();
+
+/// The bundled classifier, built once rather than for every record.
+static CLASSIFIER: OnceLock<Classifier> = OnceLock::new();
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct SpamRate {
+    /// Messages seen in the current window.
+    total: usize,
+    /// Spam messages seen in the current window.
+    spam: usize,
+    /// Spam rate of the last completed window.
+    spam_rate: Option<f64>,
+}
+
+#[smartmodule(init)]
+fn init(params: SmartModuleExtraParams) -> Result<()> {
+    let window = match params.get("window") {
+        Some(window) => window.parse()?,
+        None => DEFAULT_WINDOW,
+    };
+    if window == 0 {
+        return Err(eyre!("window must be at least one record"));
+    }
+    CLASSIFIER.get_or_init(model::classifier);
+    WINDOW
+        .set(window)
+        .map_err(|_| eyre!("window already initialized"))
+}
+
+#[smartmodule(aggregate)]
+pub fn aggregate(accumulator: RecordData, current: &Record) -> Result<RecordData> {
+    let window = *WINDOW.get().unwrap_or(&DEFAULT_WINDOW);
+    let mut state = if accumulator.as_ref().is_empty() {
+        SpamRate::default()
+    } else {
+        serde_json::from_slice::<SpamRate>(accumulator.as_ref())?
+    };
+
+    if state.total >= window {
+        state.total = 0;
+        state.spam = 0;
+    }
+
+    let sms = std::str::from_utf8(current.value.as_ref())?;
+    let prediction = CLASSIFIER.get_or_init(model::classifier).predict(sms)?;
+    state.total += 1;
+    if prediction.spam {
+        state.spam += 1;
+    }
+    if state.total == window {
+        state.spam_rate = Some(state.spam as f64 / state.total as f64);
+    }
+
+    Ok(serde_json::to_string(&state)?.into())
+}